@@ -0,0 +1,92 @@
+use crate::{
+    ctx::Ctx,
+    error::{Maybe, inner_fail},
+};
+
+pub const ARCHIVE_TAG_PREFIX: &str = "archive-";
+
+pub struct ArchiveTag {
+    pub tag: String,
+    pub timestamp: u64,
+    pub branch: String,
+}
+
+pub fn parse_archive_tag(tag: &str) -> Option<ArchiveTag> {
+    let rest = tag.strip_prefix(ARCHIVE_TAG_PREFIX)?;
+    let (timestamp, branch) = rest.split_once('-')?;
+    if branch.is_empty() {
+        return None;
+    }
+    Some(ArchiveTag {
+        tag: tag.to_string(),
+        timestamp: timestamp.parse().ok()?,
+        branch: branch.to_string(),
+    })
+}
+
+pub fn archive_tag_name(timestamp: u64, branch: &str) -> String {
+    format!("{ARCHIVE_TAG_PREFIX}{timestamp}-{branch}")
+}
+
+pub fn now_timestamp() -> Maybe<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|_| inner_fail!("Unable to get current timestamp"))?
+        .as_secs())
+}
+
+pub fn format_date(timestamp: u64) -> String {
+    let days = i64::try_from(timestamp / 86_400).unwrap_or(i64::MAX);
+
+    // Civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+pub fn list_local_archive_tags(ctx: &Ctx) -> Maybe<Vec<ArchiveTag>> {
+    let mut tags: Vec<ArchiveTag> = ctx
+        .repo
+        .tag_names(Some(&format!("{ARCHIVE_TAG_PREFIX}*")))?
+        .iter()
+        .flatten()
+        .filter_map(parse_archive_tag)
+        .collect();
+    tags.sort_by_key(|t| t.timestamp);
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{archive_tag_name, format_date, parse_archive_tag};
+
+    #[test]
+    fn round_trip() {
+        let parsed = parse_archive_tag(&archive_tag_name(1_700_000_000, "my-branch")).unwrap();
+        assert_eq!(parsed.timestamp, 1_700_000_000);
+        assert_eq!(parsed.branch, "my-branch");
+    }
+
+    #[test]
+    fn rejects_other_tags() {
+        assert!(parse_archive_tag("v1.0.0").is_none());
+        assert!(parse_archive_tag("archive-soon-branch").is_none());
+        assert!(parse_archive_tag("archive-1700000000-").is_none());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_700_000_000), "2023-11-14");
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use serde::Deserialize;
 
 #[derive(Parser)]
@@ -44,34 +44,57 @@ pub enum Commands {
     #[command(about = "Delete a branch")]
     Delete(DeleteArgs),
 
-    #[command(about = "Archive a branch by creating a tag at where it pointed, then deleting")]
+    #[command(
+        about = "Archive a branch by creating a tag at where it pointed, then deleting. Use `archive list` to see archived branches"
+    )]
     Archive(DeleteArgs),
 
+    #[command(about = "Restore an archived branch")]
+    Unarchive(UnarchiveArgs),
+
     #[command(about = "Show the commit history")]
     Log,
 
-    #[command(about = "Apply current changes to the main branch")]
-    Merge,
+    #[command(about = "Apply changes to the main branch, or land several branches in order")]
+    Merge(MergeArgs),
 
     #[command(about = "Bring the latest changes from main into this branch")]
     Sync(SyncArgs),
 
+    #[command(about = "Show which branches conflict with each other")]
+    Conflicts(ConflictsArgs),
+
+    #[command(about = "Finish resolving conflicts that were left for later")]
+    Resolve(ResolveArgs),
+
     #[command(about = "Flatten the current saves into one commit")]
     Squash(SquashArgs),
 
-    #[command(about = "Prune unneeded branches")]
-    Prune,
+    #[command(about = "Prune branches that are empty or already merged into main")]
+    Prune(PruneArgs),
 
     #[command(about = "Split the current branch into a separate workstream and switch to it")]
     Split(SplitArgs),
 
+    #[command(about = "Reorder, reword, fold, drop or split the saves on this branch")]
+    History,
+
+    #[command(about = "Change the message of a save")]
+    Reword(RewordArgs),
+
+    #[command(about = "Move a save from this branch to another branch")]
+    Move(TransferArgs),
+
+    #[command(about = "Copy a save from this branch to another branch")]
+    Copy(TransferArgs),
+
     #[command(about = "Rename the current branch")]
     Rename(RenameArgs),
 
     #[command(about = "Show interactive UI")]
     Ui,
 
-    #[command(about = "Clear out all save commits without reverting changes")]
+    #[command(about = "Undo saves without reverting changes")]
     Unsave(UnsaveArgs),
 
     #[command(about = "Undo changes to files since the last merge")]
@@ -86,6 +109,14 @@ pub struct NewArgs {
 #[derive(Args, Deserialize, Debug)]
 pub struct SplitArgs {
     pub name: Option<String>,
+
+    #[arg(
+        long,
+        num_args = 1..,
+        help = "Only move the changes to these paths onto the new branch"
+    )]
+    #[serde(default)]
+    pub files: Vec<String>,
 }
 
 #[derive(Args, Deserialize, Debug)]
@@ -98,14 +129,154 @@ pub struct DeleteArgs {
     pub names: Vec<String>,
 }
 
+#[derive(Args, Deserialize, Debug, Default)]
+pub struct PruneArgs {
+    #[arg(long, help = "Archive pruned branches instead of deleting them")]
+    pub archive: bool,
+
+    #[arg(short = 'y', long = "yes", help = "Prune without asking first")]
+    pub yes: bool,
+
+    #[arg(
+        long,
+        help = "Prune backup branches and expired archive tags on the remote instead"
+    )]
+    pub remote: bool,
+
+    #[arg(
+        long,
+        help = "Expire archive tags older than N days (itch.archive.maxAge)"
+    )]
+    pub max_age: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct UnarchiveArgs {
+    pub name: String,
+}
+
 #[derive(Args)]
 pub struct SyncArgs {
     pub names: Vec<String>,
+
+    #[arg(long, help = "Sync every branch")]
+    pub all: bool,
+
+    #[arg(
+        long,
+        help = "List the conflicts a sync would hit without changing anything"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        help = "How to sync branches with merge commits (itch.sync.merges)"
+    )]
+    pub merges: Option<MergeCommits>,
+
+    #[arg(
+        long,
+        help = "How to bring in upstream changes (branch.<name>.itchSyncMode, itch.sync.mode)"
+    )]
+    pub mode: Option<SyncMode>,
+
+    #[arg(
+        long,
+        help = "Resolve conflicts with keep, reset or later, or per file with path=choice"
+    )]
+    pub resolve: Vec<String>,
+
+    #[arg(long, help = "JSON file mapping conflicted paths to resolutions")]
+    pub resolutions: Option<String>,
 }
 
-#[derive(Args)]
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncMode {
+    Rebase,
+    Merge,
+    Squash,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeCommits {
+    Linearize,
+    Preserve,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeCleanup {
+    Keep,
+    Delete,
+    Archive,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterMerge {
+    Stay,
+    Main,
+    New,
+}
+
+#[derive(Args, Deserialize, Debug, Default)]
+pub struct MergeArgs {
+    pub names: Vec<String>,
+
+    #[arg(long, help = "Command each branch must pass before it lands")]
+    pub verify: Option<String>,
+
+    #[arg(long, help = "What to do with merged branches (itch.merge.cleanup)")]
+    pub cleanup: Option<MergeCleanup>,
+
+    #[arg(long, help = "Where to go after merging (itch.merge.then)")]
+    pub then: Option<AfterMerge>,
+
+    #[arg(
+        long,
+        overrides_with = "no_prune",
+        help = "Prune other empty branches (itch.merge.prune)"
+    )]
+    #[serde(default)]
+    pub prune: bool,
+
+    #[arg(
+        long,
+        overrides_with = "prune",
+        help = "Don't prune, even if itch.merge.prune is set"
+    )]
+    #[serde(default)]
+    pub no_prune: bool,
+
+    #[arg(long, help = "Merge even if files still have conflict markers")]
+    #[serde(default)]
+    pub allow_conflicts: bool,
+}
+
+#[derive(Args, Default)]
 pub struct SaveArgs {
     pub message: Vec<String>,
+
+    #[arg(long, help = "Save even if files still have conflict markers")]
+    pub allow_conflicts: bool,
+}
+
+#[derive(Args)]
+pub struct ConflictsArgs {
+    pub names: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct ResolveArgs {
+    pub paths: Vec<String>,
+
+    #[arg(long, help = "Only list the files that still need resolving")]
+    pub list: bool,
+
+    #[arg(long, help = "Mark files as resolved without editing them")]
+    pub done: bool,
 }
 
 #[derive(Args, Deserialize, Debug)]
@@ -135,6 +306,27 @@ pub struct RevertArgs {
 
 #[derive(Args, Deserialize, Debug)]
 pub struct UnsaveArgs {
+    #[arg(
+        help = "Number of saves, `to <save>`, or files, folders and globs to take out of the last save"
+    )]
+    pub args: Vec<String>,
+
+    #[arg(long, help = "Unsave everything back to the fork point")]
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Args)]
+pub struct RewordArgs {
+    #[arg(
+        help = "Save number (1 is the latest), id prefix or a word from its message, then the new message"
+    )]
+    pub args: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct TransferArgs {
+    #[arg(help = "Save number (1 is the latest), id prefix or message, then `to <branch>`")]
     pub args: Vec<String>,
 }
 
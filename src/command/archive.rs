@@ -1,23 +1,83 @@
 use crate::{
+    archive::{
+        ARCHIVE_TAG_PREFIX, archive_tag_name, format_date, list_local_archive_tags, now_timestamp,
+        parse_archive_tag,
+    },
     cli::DeleteArgs,
     command::delete::delete_command,
     ctx::Ctx,
-    error::{Attempt, inner_fail},
-    remote::push_tag,
+    error::{Attempt, Maybe},
+    print::show_warning,
+    remote::{get_remote_prefix, list_remote_refs, push_tag},
     reset::pop_and_reset,
     save::save_temp,
 };
 
+fn describe_commit(ctx: &Ctx, id: git2::Oid) -> String {
+    match ctx.repo.find_commit(id) {
+        Ok(commit) => commit.summary().unwrap_or("<invalid message>").to_string(),
+        Err(_) => "not fetched".to_string(),
+    }
+}
+
+fn list_remote_only_archives(ctx: &Ctx) -> Maybe<Vec<(String, git2::Oid)>> {
+    let prefix = format!("refs/tags/{}", get_remote_prefix()?);
+    let mut remote_only = vec![];
+    for (name, id) in list_remote_refs(ctx)? {
+        if let Some(tag) = name.strip_prefix(&prefix)
+            && tag.starts_with(ARCHIVE_TAG_PREFIX)
+            && !tag.ends_with("^{}")
+            && ctx
+                .repo
+                .find_reference(&format!("refs/tags/{tag}"))
+                .is_err()
+        {
+            remote_only.push((tag.to_string(), id));
+        }
+    }
+    Ok(remote_only)
+}
+
+pub fn archive_list_command(ctx: &Ctx) -> Attempt {
+    for tag in list_local_archive_tags(ctx)? {
+        let id = ctx
+            .repo
+            .find_reference(&format!("refs/tags/{}", tag.tag))?
+            .peel_to_commit()?
+            .id();
+        println!(
+            "{} {} ({})",
+            format_date(tag.timestamp),
+            tag.branch,
+            describe_commit(ctx, id)
+        );
+    }
+
+    match list_remote_only_archives(ctx) {
+        Ok(remote_only) => {
+            for (name, id) in remote_only {
+                if let Some(tag) = parse_archive_tag(&name) {
+                    println!(
+                        "{} {} ({}) [remote]",
+                        format_date(tag.timestamp),
+                        tag.branch,
+                        describe_commit(ctx, id)
+                    );
+                }
+            }
+        }
+        Err(e) => show_warning(ctx, &format!("Failed to list remote archives ({e})")),
+    }
+
+    Ok(())
+}
+
 pub fn archive_command(ctx: &Ctx, args: &DeleteArgs) -> Attempt {
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|_| inner_fail!("Unable to get current timestamp"))?
-        .as_secs()
-        .to_string();
+    let now = now_timestamp()?;
     save_temp(ctx, "Save before archive".to_string())?;
     for branch_name in &args.names {
         let branch = ctx.repo.find_branch(branch_name, git2::BranchType::Local)?;
-        let tag_name = format!("archive-{now}-{branch_name}");
+        let tag_name = archive_tag_name(now, branch_name);
         ctx.repo.tag_lightweight(
             &tag_name,
             branch.into_reference().peel_to_commit()?.as_object(),
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use git2::{Commit, IndexConflict, MergeOptions};
+
+use crate::{
+    cli::ConflictsArgs,
+    ctx::Ctx,
+    diff::good_diff_options,
+    error::{Attempt, Maybe, fail},
+};
+
+pub struct BranchOverlap {
+    pub first: String,
+    pub second: String,
+    pub paths: Vec<String>,
+}
+
+pub struct ConflictMatrix {
+    pub names: Vec<String>,
+    pub overlaps: Vec<BranchOverlap>,
+}
+
+impl ConflictMatrix {
+    pub fn paths(&self, first: &str, second: &str) -> Option<&[String]> {
+        self.overlaps
+            .iter()
+            .find(|o| {
+                (o.first == first && o.second == second) || (o.first == second && o.second == first)
+            })
+            .map(|o| o.paths.as_slice())
+    }
+}
+
+struct BranchChanges<'a> {
+    name: String,
+    commit: Commit<'a>,
+    paths: HashSet<String>,
+}
+
+fn changed_paths(ctx: &Ctx, main: &Commit, branch: &Commit) -> Maybe<HashSet<String>> {
+    let repo = &ctx.repo;
+    let fork = repo.find_commit(repo.merge_base(main.id(), branch.id())?)?;
+    let mut diff = repo.diff_tree_to_tree(
+        Some(&fork.tree()?),
+        Some(&branch.tree()?),
+        Some(&mut good_diff_options()),
+    )?;
+    diff.find_similar(None)?;
+
+    let mut paths = HashSet::new();
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path() {
+                paths.insert(path.to_string_lossy().into_owned());
+            }
+        }
+    }
+    Ok(paths)
+}
+
+fn branch_changes<'a>(ctx: &'a Ctx, names: &[String]) -> Maybe<Vec<BranchChanges<'a>>> {
+    let repo = &ctx.repo;
+    let main = repo
+        .find_branch("main", git2::BranchType::Local)?
+        .into_reference()
+        .peel_to_commit()?;
+
+    let names = if names.is_empty() {
+        let mut names = vec![];
+        for branch in repo.branches(Some(git2::BranchType::Local))? {
+            if let Some(name) = branch?.0.name()?
+                && name != "main"
+            {
+                names.push(name.to_string());
+            }
+        }
+        names
+    } else {
+        names.to_vec()
+    };
+
+    let mut changes = vec![];
+    for name in names {
+        let commit = repo
+            .find_branch(&name, git2::BranchType::Local)?
+            .into_reference()
+            .peel_to_commit()?;
+        let paths = changed_paths(ctx, &main, &commit)?;
+        if !paths.is_empty() {
+            changes.push(BranchChanges {
+                name,
+                commit,
+                paths,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+fn conflict_paths(conflict: &IndexConflict) -> Vec<String> {
+    [&conflict.our, &conflict.their, &conflict.ancestor]
+        .into_iter()
+        .flatten()
+        .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+        .collect()
+}
+
+fn overlapping_paths(
+    ctx: &Ctx,
+    first: &BranchChanges,
+    second: &BranchChanges,
+) -> Maybe<Vec<String>> {
+    let mut options = MergeOptions::new();
+    options.find_renames(true);
+    let index = ctx
+        .repo
+        .merge_commits(&first.commit, &second.commit, Some(&options))?;
+    if !index.has_conflicts() {
+        return Ok(vec![]);
+    }
+
+    let mut paths = vec![];
+    for conflict in index.conflicts()? {
+        let candidates = conflict_paths(&conflict?);
+        if candidates.iter().any(|p| first.paths.contains(p))
+            && candidates.iter().any(|p| second.paths.contains(p))
+            && let Some(path) = candidates.into_iter().next()
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+pub fn conflict_matrix(ctx: &Ctx, names: &[String]) -> Maybe<ConflictMatrix> {
+    let changes = branch_changes(ctx, names)?;
+
+    let mut overlaps = vec![];
+    for (i, first) in changes.iter().enumerate() {
+        for second in &changes[i + 1..] {
+            let paths = overlapping_paths(ctx, first, second)?;
+            if !paths.is_empty() {
+                overlaps.push(BranchOverlap {
+                    first: first.name.clone(),
+                    second: second.name.clone(),
+                    paths,
+                });
+            }
+        }
+    }
+
+    Ok(ConflictMatrix {
+        names: changes.into_iter().map(|c| c.name).collect(),
+        overlaps,
+    })
+}
+
+pub fn conflicts_command(ctx: &Ctx, args: &ConflictsArgs) -> Attempt {
+    let matrix = conflict_matrix(ctx, &args.names)?;
+    if matrix.names.len() < 2 {
+        return fail!("Need at least two branches with changes to compare.");
+    }
+
+    if matrix.overlaps.is_empty() {
+        println!("No conflicts between {} branches.", matrix.names.len());
+        return Ok(());
+    }
+
+    let name_width = matrix.names.iter().map(String::len).max().unwrap_or(0);
+    let column_width = matrix.names.len().to_string().len().max(2);
+
+    print!("{:width$}  ", "", width = name_width + column_width + 1);
+    for i in 1..=matrix.names.len() {
+        print!(" {i:>column_width$}");
+    }
+    println!();
+
+    for (i, first) in matrix.names.iter().enumerate() {
+        print!("{:>column_width$} {first:name_width$}  ", i + 1);
+        for second in &matrix.names {
+            let cell = if first == second {
+                "-".to_string()
+            } else {
+                match matrix.paths(first, second) {
+                    Some(paths) => paths.len().to_string(),
+                    None => ".".to_string(),
+                }
+            };
+            print!(" {cell:>column_width$}");
+        }
+        println!();
+    }
+
+    println!();
+    for overlap in &matrix.overlaps {
+        println!(
+            "{} and {}: {}",
+            overlap.first,
+            overlap.second,
+            overlap.paths.join(", ")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use std::cell::Cell;
+
+use git2::{ApplyOptions, Commit, Diff, MergeOptions, Patch, ResetType, Tree};
+
+use crate::{
+    branch::get_current_branch,
+    commit::{list_saves, short_summary},
+    ctx::Ctx,
+    editor::edit_temp_text,
+    error::{Attempt, Maybe, fail},
+    history::{
+        HistoryStep, HunkInfo, PlanAction, StepAction, parse_hunk_groups, parse_plan, render_hunks,
+        render_plan, split_pieces, with_summary,
+    },
+    remote::try_push_branch,
+    reset::pop_and_reset,
+    save::save_temp,
+};
+
+pub fn branch_saves(ctx: &Ctx) -> Maybe<Vec<Commit<'_>>> {
+    let mut saves = list_saves(ctx, &ctx.repo.head()?.peel_to_commit()?)?;
+    saves.reverse();
+    Ok(saves)
+}
+
+fn save_diff<'a>(ctx: &'a Ctx, commit: &Commit) -> Maybe<Diff<'a>> {
+    Ok(ctx.repo.diff_tree_to_tree(
+        Some(&commit.parent(0)?.tree()?),
+        Some(&commit.tree()?),
+        None,
+    )?)
+}
+
+pub fn list_hunks(ctx: &Ctx, commit: &Commit) -> Maybe<Vec<HunkInfo>> {
+    let diff = save_diff(ctx, commit)?;
+    let mut hunks = vec![];
+    for i in 0..diff.deltas().len() {
+        let Some(patch) = Patch::from_diff(&diff, i)? else {
+            continue;
+        };
+        let delta_path = patch
+            .delta()
+            .new_file()
+            .path()
+            .or_else(|| patch.delta().old_file().path())
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        for h in 0..patch.num_hunks() {
+            let (hunk, line_count) = patch.hunk(h)?;
+            let mut preview = vec![];
+            for l in 0..line_count {
+                let line = patch.line_in_hunk(h, l)?;
+                if matches!(line.origin(), '+' | '-') && preview.len() < 3 {
+                    preview.push(format!(
+                        "{}{}",
+                        line.origin(),
+                        String::from_utf8_lossy(line.content()).trim_end()
+                    ));
+                }
+            }
+            hunks.push(HunkInfo {
+                path: delta_path.clone(),
+                header: String::from_utf8_lossy(hunk.header()).trim().to_string(),
+                preview,
+            });
+        }
+    }
+    Ok(hunks)
+}
+
+fn split_trees<'a>(ctx: &'a Ctx, commit: &Commit<'a>, groups: &[usize]) -> Maybe<Vec<Tree<'a>>> {
+    let diff = save_diff(ctx, commit)?;
+    let base = commit.parent(0)?.tree()?;
+    let pieces = split_pieces(groups);
+
+    let mut ranges = vec![];
+    let mut start = 0;
+    for i in 0..diff.deltas().len() {
+        let count = Patch::from_diff(&diff, i)?.map_or(0, |p| p.num_hunks());
+        ranges.push(start..start + count);
+        start += count;
+    }
+
+    let mut trees = vec![];
+    for piece in &pieces[..pieces.len().saturating_sub(1)] {
+        let delta = Cell::new(0);
+        let hunk = Cell::new(0);
+        let mut options = ApplyOptions::new();
+        options.delta_callback(|_| {
+            let range = ranges.get(delta.get()).cloned().unwrap_or_default();
+            delta.set(delta.get() + 1);
+            hunk.set(range.start);
+            range.is_empty() || groups[range].iter().any(|g| g <= piece)
+        });
+        options.hunk_callback(|_| {
+            let index = hunk.get();
+            hunk.set(index + 1);
+            groups.get(index).is_some_and(|g| g <= piece)
+        });
+        let mut index = ctx.repo.apply_to_tree(&base, &diff, Some(&mut options))?;
+        trees.push(ctx.repo.find_tree(index.write_tree_to(&ctx.repo)?)?);
+    }
+    trees.push(commit.tree()?);
+    Ok(trees)
+}
+
+fn apply_change<'a>(
+    ctx: &'a Ctx,
+    label: &Commit,
+    base: &Tree,
+    change: &Tree,
+    onto: &Commit,
+) -> Maybe<Tree<'a>> {
+    let repo = &ctx.repo;
+    let mut options = MergeOptions::new();
+    options.find_renames(true);
+    let mut index = repo.merge_trees(base, &onto.tree()?, change, Some(&options))?;
+
+    if index.has_conflicts() {
+        let mut paths = vec![];
+        for conflict in index.conflicts()? {
+            let conflict = conflict?;
+            if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+                paths.push(String::from_utf8_lossy(&entry.path).into_owned());
+            }
+        }
+        return fail!(format!(
+            "{} conflicts in {} after the new order. Nothing was changed.",
+            short_summary(label),
+            paths.join(", ")
+        ));
+    }
+
+    Ok(repo.find_tree(index.write_tree_to(repo)?)?)
+}
+
+fn commit_like<'a>(
+    ctx: &'a Ctx,
+    original: &Commit,
+    message: &str,
+    tree: &Tree,
+    parents: &[&Commit],
+) -> Maybe<Commit<'a>> {
+    let id = ctx.repo.commit(
+        None,
+        &original.author(),
+        &original.committer(),
+        message,
+        tree,
+        parents,
+    )?;
+    Ok(ctx.repo.find_commit(id)?)
+}
+
+fn run_step<'a>(ctx: &'a Ctx, step: &HistoryStep, current: Commit<'a>) -> Maybe<Commit<'a>> {
+    let commit = ctx.repo.find_commit(step.id)?;
+    let message = commit.message().unwrap_or_default();
+    let parent_tree = commit.parent(0)?.tree()?;
+
+    match &step.action {
+        StepAction::Drop => Ok(current),
+        StepAction::Pick => {
+            let tree = apply_change(ctx, &commit, &parent_tree, &commit.tree()?, &current)?;
+            commit_like(ctx, &commit, message, &tree, &[&current])
+        }
+        StepAction::Reword(message) => {
+            let tree = apply_change(ctx, &commit, &parent_tree, &commit.tree()?, &current)?;
+            commit_like(ctx, &commit, message, &tree, &[&current])
+        }
+        StepAction::Fold => {
+            let tree = apply_change(ctx, &commit, &parent_tree, &commit.tree()?, &current)?;
+            let parents: Vec<Commit> = current.parents().collect();
+            let parent_refs: Vec<&Commit> = parents.iter().collect();
+            commit_like(
+                ctx,
+                &current,
+                current.message().unwrap_or_default(),
+                &tree,
+                &parent_refs,
+            )
+        }
+        StepAction::Split(groups) => {
+            let trees = split_trees(ctx, &commit, groups)?;
+            let summary = commit.summary().unwrap_or_default();
+            let mut current = current;
+            let mut base = parent_tree;
+            for (i, piece) in trees.iter().enumerate() {
+                let tree = apply_change(ctx, &commit, &base, piece, &current)?;
+                let message =
+                    with_summary(message, &format!("{summary} ({}/{})", i + 1, trees.len()));
+                current = commit_like(ctx, &commit, &message, &tree, &[&current])?;
+                base = piece.clone();
+            }
+            Ok(current)
+        }
+    }
+}
+
+pub fn rewrite_history(ctx: &Ctx, steps: &[HistoryStep]) -> Attempt {
+    let saves = branch_saves(ctx)?;
+    let Some(first) = saves.first() else {
+        return fail!("There are no saves to edit.");
+    };
+    let branch_name = get_current_branch(ctx)?;
+    if let Some(merge) = saves.iter().find(|c| c.parent_count() > 1) {
+        return fail!(format!(
+            "{} is a merge commit. Sync with --merges linearize before editing history.",
+            short_summary(merge)
+        ));
+    }
+    let fork = first.parent(0)?;
+    let top = saves.last().map(Commit::id);
+
+    save_temp(ctx, "Save before history edit".to_string())?;
+
+    let result = (|| -> Attempt {
+        let mut current = fork;
+        for step in steps {
+            current = run_step(ctx, step, current)?;
+        }
+
+        let mut unsaved = vec![];
+        let mut commit = ctx.repo.head()?.peel_to_commit()?;
+        while Some(commit.id()) != top {
+            let parent = commit.parent(0)?;
+            unsaved.push(commit);
+            commit = parent;
+        }
+        for commit in unsaved.iter().rev() {
+            let step = HistoryStep {
+                id: commit.id(),
+                action: StepAction::Pick,
+            };
+            current = run_step(ctx, &step, current)?;
+        }
+
+        ctx.repo.reset(current.as_object(), ResetType::Hard, None)?;
+        Ok(())
+    })();
+
+    pop_and_reset(ctx)?;
+    result?;
+
+    try_push_branch(ctx, &branch_name);
+    Ok(())
+}
+
+fn find_listed<'a, 'b>(saves: &'b [Commit<'a>], id: &str) -> Maybe<&'b Commit<'a>> {
+    let id = id.to_lowercase();
+    let matches: Vec<&Commit> = saves
+        .iter()
+        .filter(|c| c.id().to_string().starts_with(&id))
+        .collect();
+    match matches.as_slice() {
+        [commit] => Ok(commit),
+        [] => fail!(format!("{id} is not a save on this branch.")),
+        _ => fail!(format!("{id} matches several saves.")),
+    }
+}
+
+fn edit_split(ctx: &Ctx, commit: &Commit) -> Maybe<Vec<usize>> {
+    let hunks = list_hunks(ctx, commit)?;
+    if hunks.len() < 2 {
+        return fail!(format!(
+            "{} has only one hunk and cannot be split.",
+            short_summary(commit)
+        ));
+    }
+    let text = edit_temp_text(
+        &render_hunks(commit.summary().unwrap_or_default(), &hunks),
+        None,
+    )?;
+    parse_hunk_groups(&text, hunks.len())
+}
+
+pub fn history_command(ctx: &Ctx) -> Attempt {
+    let saves = branch_saves(ctx)?;
+    if saves.is_empty() {
+        return fail!("There are no saves to edit.");
+    }
+
+    let listed: Vec<(String, String)> = saves
+        .iter()
+        .map(|c| {
+            (
+                c.id().to_string()[0..8].to_string(),
+                c.summary().unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+
+    let plan = parse_plan(&edit_temp_text(&render_plan(&listed), None)?)?;
+    if plan.is_empty() {
+        if ctx.can_prompt() {
+            eprintln!("Nothing to do.");
+        }
+        return Ok(());
+    }
+    let mut steps = vec![];
+    for line in plan {
+        let commit = find_listed(&saves, &line.id)?;
+        if steps.iter().any(|s: &HistoryStep| s.id == commit.id()) {
+            return fail!(format!("{} is listed more than once.", line.id));
+        }
+        let action = match line.action {
+            PlanAction::Pick => StepAction::Pick,
+            PlanAction::Reword => StepAction::Reword(with_summary(
+                commit.message().unwrap_or_default(),
+                &line.message,
+            )),
+            PlanAction::Fold => StepAction::Fold,
+            PlanAction::Drop => StepAction::Drop,
+            PlanAction::Split => StepAction::Split(edit_split(ctx, commit)?),
+        };
+        steps.push(HistoryStep {
+            id: commit.id(),
+            action,
+        });
+    }
+
+    rewrite_history(ctx, &steps)
+}
@@ -1,6 +1,8 @@
 use git2::{Commit, ErrorCode};
 
-use crate::{commit::count_commits_since, ctx::Ctx, error::Attempt};
+use crate::{commit::count_commits_since, ctx::Ctx, error::Attempt, sync::conflict_badge};
+
+use super::sync::sync_conflict_count;
 
 pub fn list_command(ctx: &Ctx) -> Attempt {
     let (selected_color, muted_color, clear_color) = if ctx.color_enabled() {
@@ -37,7 +39,7 @@ pub fn list_command(ctx: &Ctx) -> Attempt {
 
                         match ctx
                             .repo
-                            .merge_base(commit.id(), b.0.into_reference().peel_to_commit()?.id())
+                            .merge_base(commit.id(), b.0.get().peel_to_commit()?.id())
                         {
                             Ok(fork_id) => {
                                 let fork_commit = ctx.repo.find_commit(fork_id)?;
@@ -45,7 +47,10 @@ pub fn list_command(ctx: &Ctx) -> Attempt {
                                 let behind = count_commits_since(ctx, &fork_commit, commit)?;
 
                                 if behind > 0 {
-                                    println!("{muted_color} {behind} behind{clear_color}");
+                                    let badge = conflict_badge(sync_conflict_count(ctx, name))
+                                        .map(|b| format!(", {b}"))
+                                        .unwrap_or_default();
+                                    println!("{muted_color} {behind} behind{badge}{clear_color}");
                                 } else {
                                     println!();
                                 }
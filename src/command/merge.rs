@@ -1,11 +1,52 @@
-use git2::Oid;
+use std::process::Command;
+
+use git2::{Commit, Oid, build::CheckoutBuilder};
 
 use crate::{
+    branch::get_current_branch,
+    cli::{AfterMerge, DeleteArgs, LoadArgs, MergeArgs, MergeCleanup, NewArgs, PruneArgs},
+    config::{get_config_bool, get_config_enum, get_config_string},
     ctx::Ctx,
-    error::{Attempt, Maybe, fail},
+    deferred::unresolved_in_tree,
+    error::{Attempt, Maybe, fail, inner_fail},
+    print::show_warning,
     remote::{try_pull_main, try_push_main},
+    reset::{pop_and_reset, skip_temp_commits},
+    save::save_temp,
+    sync::{SyncDetails, SyncOptions},
+};
+
+use super::{
+    archive::archive_command, delete::delete_command, load::load_command, new::new_command,
+    prune::prune_command, sync::try_sync_branch,
 };
 
+pub enum Landing {
+    Landed,
+    Conflicted(Vec<String>),
+    FailedVerification,
+}
+
+pub struct MergeQueueReport {
+    pub results: Vec<(String, Landing)>,
+}
+
+impl MergeQueueReport {
+    pub fn landed(&self) -> Vec<&str> {
+        self.results
+            .iter()
+            .filter(|(_, l)| matches!(l, Landing::Landed))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    pub fn all_landed(&self) -> bool {
+        self.results
+            .iter()
+            .all(|(_, l)| matches!(l, Landing::Landed))
+    }
+}
+
 fn combine_branches(ctx: &Ctx) -> Maybe<Oid> {
     let repo = &ctx.repo;
 
@@ -24,7 +65,227 @@ fn combine_branches(ctx: &Ctx) -> Maybe<Oid> {
     fail!("Must be synced on main")
 }
 
-pub fn merge_command(ctx: &Ctx) -> Attempt {
+fn resolve_verify_command(ctx: &Ctx, args: &MergeArgs) -> Maybe<Option<String>> {
+    if let Some(command) = &args.verify {
+        return Ok(Some(command.clone()));
+    }
+    get_config_string(ctx, "itch.verify")
+}
+
+fn verify_commit(ctx: &Ctx, commit: &Commit, command: &str) -> Maybe<bool> {
+    let dir = tempfile::tempdir()?;
+
+    let mut options = CheckoutBuilder::new();
+    options.force();
+    options.update_index(false);
+    options.target_dir(dir.path());
+    ctx.repo
+        .checkout_tree(commit.tree()?.as_object(), Some(&mut options))?;
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir.path())
+        .status()
+        .map_err(|_| inner_fail!("Failed to start verification command."))?;
+
+    Ok(status.success())
+}
+
+fn check_unresolved(ctx: &Ctx, args: &MergeArgs, branch_name: &str, commit: &Commit) -> Attempt {
+    if args.allow_conflicts {
+        return Ok(());
+    }
+    let unresolved = unresolved_in_tree(ctx, branch_name, &commit.tree()?)?;
+    if unresolved.is_empty() {
+        return Ok(());
+    }
+    fail!(format!(
+        "Conflict markers remain on {branch_name} in {}. Run `itch resolve` or pass --allow-conflicts.",
+        unresolved.join(", ")
+    ))
+}
+
+fn land_branch(
+    ctx: &Ctx,
+    args: &MergeArgs,
+    branch_name: &str,
+    verify: Option<&str>,
+) -> Maybe<Landing> {
+    if branch_name == "main" {
+        return fail!("Cannot merge main into itself.");
+    }
+
+    let options = SyncOptions {
+        no_prompt: true,
+        ..Default::default()
+    };
+
+    if let SyncDetails::Conflicted(conflicts) = try_sync_branch(ctx, branch_name, &options)?.details
+    {
+        return Ok(Landing::Conflicted(
+            conflicts.iter().map(|c| c.path().to_string()).collect(),
+        ));
+    }
+
+    let commit = skip_temp_commits(
+        ctx.repo
+            .find_branch(branch_name, git2::BranchType::Local)?
+            .into_reference()
+            .peel_to_commit()?,
+    );
+
+    check_unresolved(ctx, args, branch_name, &commit)?;
+
+    if let Some(command) = verify
+        && !verify_commit(ctx, &commit, command)?
+    {
+        return Ok(Landing::FailedVerification);
+    }
+
+    let mut main_ref = ctx
+        .repo
+        .find_branch("main", git2::BranchType::Local)?
+        .into_reference();
+
+    let main_id = main_ref.peel_to_commit()?.id();
+
+    if main_id != commit.id() && !ctx.repo.graph_descendant_of(commit.id(), main_id)? {
+        return fail!(format!(
+            "{branch_name} did not end up on top of main after syncing."
+        ));
+    }
+
+    main_ref.set_target(commit.id(), &format!("Merged from {branch_name}"))?;
+
+    Ok(Landing::Landed)
+}
+
+pub fn run_merge_queue(ctx: &Ctx, args: &MergeArgs) -> Maybe<MergeQueueReport> {
+    let verify = resolve_verify_command(ctx, args)?;
+
+    let on_main = get_current_branch(ctx)? == "main";
+
+    if on_main {
+        if has_unsaved_changes(ctx)? {
+            return fail!("Save or revert changes on main before merging other branches.");
+        }
+    } else {
+        save_temp(ctx, "Save before merge".to_string())?;
+    }
+
+    try_pull_main(ctx);
+
+    let mut report = MergeQueueReport { results: vec![] };
+
+    let queue_result = args.names.iter().try_for_each(|branch_name| -> Attempt {
+        let landing = land_branch(ctx, args, branch_name, verify.as_deref())?;
+        report.results.push((branch_name.clone(), landing));
+        Ok(())
+    });
+
+    if on_main {
+        let mut options = CheckoutBuilder::new();
+        options.force();
+        ctx.repo.checkout_head(Some(&mut options))?;
+    } else {
+        pop_and_reset(ctx)?;
+    }
+
+    if !report.landed().is_empty() {
+        try_push_main(ctx);
+    }
+
+    queue_result?;
+
+    Ok(report)
+}
+
+fn has_unsaved_changes(ctx: &Ctx) -> Maybe<bool> {
+    let statuses = ctx.repo.statuses(None)?;
+    Ok(statuses.iter().any(|s| s.status() != git2::Status::IGNORED))
+}
+
+pub fn cleanup_merged(ctx: &Ctx, args: &MergeArgs, merged: &[String]) -> Attempt {
+    let cleanup = match args.cleanup {
+        Some(c) => c,
+        None => get_config_enum(ctx, "itch.merge.cleanup")?.unwrap_or(MergeCleanup::Keep),
+    };
+    let then = match args.then {
+        Some(t) => t,
+        None => get_config_enum(ctx, "itch.merge.then")?.unwrap_or(AfterMerge::Stay),
+    };
+    let prune = match (args.prune, args.no_prune) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => get_config_bool(ctx, "itch.merge.prune")?.unwrap_or(false),
+    };
+
+    let current_branch = get_current_branch(ctx)?;
+    let keep_current = cleanup == MergeCleanup::Delete && has_unsaved_changes(ctx)?;
+
+    let mut names: Vec<String> = vec![];
+    for name in merged {
+        if keep_current && *name == current_branch {
+            show_warning(
+                ctx,
+                &format!("Keeping {name} because it has unsaved changes."),
+            );
+        } else {
+            names.push(name.clone());
+        }
+    }
+
+    if !names.is_empty() {
+        let delete_args = DeleteArgs { names };
+        match cleanup {
+            MergeCleanup::Keep => {}
+            MergeCleanup::Delete => delete_command(ctx, &delete_args)?,
+            MergeCleanup::Archive => archive_command(ctx, &delete_args)?,
+        }
+    }
+
+    if prune {
+        prune_command(
+            ctx,
+            &PruneArgs {
+                yes: true,
+                ..Default::default()
+            },
+        )?;
+    }
+
+    match then {
+        AfterMerge::Stay => {}
+        AfterMerge::Main => {
+            if get_current_branch(ctx)? != "main" {
+                load_command(
+                    ctx,
+                    &LoadArgs {
+                        name: "main".to_string(),
+                    },
+                )?;
+            }
+        }
+        AfterMerge::New => new_command(ctx, &NewArgs { name: None })?,
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &MergeQueueReport) {
+    for (name, landing) in &report.results {
+        match landing {
+            Landing::Landed => eprintln!("Landed: {name}"),
+            Landing::Conflicted(paths) => {
+                eprintln!("Stopped on conflicts: {name} ({})", paths.join(", "));
+            }
+            Landing::FailedVerification => eprintln!("Failed verification: {name}"),
+        }
+    }
+}
+
+fn merge_current(ctx: &Ctx, args: &MergeArgs) -> Attempt {
     let head = ctx.repo.head()?;
     let head_name = head.name().expect("No valid head name.");
 
@@ -36,6 +297,19 @@ pub fn merge_command(ctx: &Ctx) -> Attempt {
 
     let resolved_commit = combine_branches(ctx)?;
 
+    check_unresolved(
+        ctx,
+        args,
+        &get_current_branch(ctx)?,
+        &ctx.repo.find_commit(resolved_commit)?,
+    )?;
+
+    if let Some(command) = resolve_verify_command(ctx, args)?
+        && !verify_commit(ctx, &ctx.repo.find_commit(resolved_commit)?, &command)?
+    {
+        return fail!("Verification failed, not merging.");
+    }
+
     let reflog_message = format!("Merged from {head_name}");
 
     ctx.repo
@@ -45,5 +319,26 @@ pub fn merge_command(ctx: &Ctx) -> Attempt {
 
     try_push_main(ctx);
 
+    cleanup_merged(ctx, args, &[get_current_branch(ctx)?])
+}
+
+pub fn merge_command(ctx: &Ctx, args: &MergeArgs) -> Attempt {
+    if args.names.is_empty() {
+        return merge_current(ctx, args);
+    }
+
+    let report = run_merge_queue(ctx, args)?;
+
+    if ctx.can_prompt() {
+        print_report(&report);
+    }
+
+    let landed: Vec<String> = report.landed().into_iter().map(String::from).collect();
+    cleanup_merged(ctx, args, &landed)?;
+
+    if !report.all_landed() {
+        return fail!("Not every branch could be merged.");
+    }
+
     Ok(())
 }
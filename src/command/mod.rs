@@ -1,14 +1,20 @@
 use std::io::IsTerminal;
 use std::{env, io::stdout};
 
+use conflicts::conflicts_command;
 use connect::connect_command;
 use disconnect::disconnect_command;
+use history::history_command;
 use init::init_command;
 use rename::rename_command;
+use resolve::resolve_command;
 use revert::revert_command;
+use reword::reword_command;
 use split::split_command;
+use transfer::{copy_command, move_command};
 
-use crate::command::archive::archive_command;
+use crate::command::archive::{archive_command, archive_list_command};
+use crate::command::unarchive::unarchive_command;
 use crate::error::{Attempt, fail};
 use crate::{
     cli::{Cli, Commands},
@@ -23,10 +29,12 @@ use self::{
 };
 
 mod archive;
+mod conflicts;
 mod connect;
 mod delete;
 mod diff;
 mod disconnect;
+mod history;
 mod init;
 mod list;
 mod load;
@@ -35,13 +43,17 @@ mod merge;
 mod new;
 mod prune;
 mod rename;
+mod resolve;
 mod revert;
+mod reword;
 mod save;
 mod split;
 mod squash;
 mod status;
 mod sync;
+mod transfer;
 mod ui;
+mod unarchive;
 mod unsave;
 
 pub fn run_command(cli: &Cli) -> Attempt {
@@ -60,8 +72,12 @@ pub fn run_command(cli: &Cli) -> Attempt {
     }
 
     match &cli.command {
-        Commands::Archive(args) => archive_command(&ctx, args),
+        Commands::Archive(args) => match args.names.as_slice() {
+            [word] if word == "list" => archive_list_command(&ctx),
+            _ => archive_command(&ctx, args),
+        },
         Commands::Init => fail!("Unexpected command after block"),
+        Commands::Conflicts(args) => conflicts_command(&ctx, args),
         Commands::Connect(args) => connect_command(&ctx, args),
         Commands::Disconnect => disconnect_command(&ctx),
         Commands::Delete(args) => delete_command(&ctx, args),
@@ -69,16 +85,22 @@ pub fn run_command(cli: &Cli) -> Attempt {
         Commands::List => list_command(&ctx),
         Commands::Load(args) => load_command(&ctx, args),
         Commands::Log => log_command(&ctx),
-        Commands::Merge => merge_command(&ctx),
+        Commands::Merge(args) => merge_command(&ctx, args),
         Commands::New(args) => new_command(&ctx, args),
-        Commands::Prune => prune_command(&ctx),
+        Commands::Prune(args) => prune_command(&ctx, args),
         Commands::Save(args) => save_command(&ctx, args, false),
         Commands::Split(args) => split_command(&ctx, args),
+        Commands::History => history_command(&ctx),
+        Commands::Reword(args) => reword_command(&ctx, args),
+        Commands::Move(args) => move_command(&ctx, args),
+        Commands::Copy(args) => copy_command(&ctx, args),
         Commands::Rename(args) => rename_command(&ctx, args),
+        Commands::Resolve(args) => resolve_command(&ctx, args),
         Commands::Squash(args) => squash_command(&ctx, args),
         Commands::Status(args) => status_command(&ctx, args),
         Commands::Sync(args) => sync_command(&ctx, args),
         Commands::Ui => ui_command(&ctx),
+        Commands::Unarchive(args) => unarchive_command(&ctx, args),
         Commands::Unsave(args) => unsave_command(&ctx, args),
         Commands::Revert(args) => revert_command(&ctx, args),
     }
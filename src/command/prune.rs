@@ -1,21 +1,102 @@
-use git2::BranchType;
+use std::collections::HashSet;
+
+use git2::{BranchType, Commit, Oid, Tree};
 
 use crate::{
-    cli::DeleteArgs, command::delete::delete_command, ctx::Ctx, error::Attempt,
-    reset::pop_and_reset, save::save_temp,
+    archive::{ArchiveTag, list_local_archive_tags, now_timestamp, parse_archive_tag},
+    branch::local_branch_exists,
+    cli::{DeleteArgs, PruneArgs},
+    command::{archive::archive_command, delete::delete_command},
+    config::get_config_i64,
+    ctx::Ctx,
+    error::{Attempt, Maybe},
+    print::show_warning,
+    prompt::ask_option,
+    remote::{delete_remote_refs, get_remote_prefix, list_remote_refs},
+    reset::pop_and_reset,
+    save::save_temp,
 };
 
-pub fn prune_command(ctx: &Ctx) -> Attempt {
-    let mut branches_to_delete: Vec<String> = vec![];
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+enum PruneReason {
+    Empty,
+    Merged,
+}
+
+fn patch_id(ctx: &Ctx, old_tree: &Tree, new_tree: &Tree) -> Maybe<Oid> {
+    let diff = ctx
+        .repo
+        .diff_tree_to_tree(Some(old_tree), Some(new_tree), None)?;
+    Ok(diff.patchid(None)?)
+}
+
+fn commit_patch_id(ctx: &Ctx, commit: &Commit) -> Maybe<Oid> {
+    let parent_tree = commit.parent(0)?.tree()?;
+    patch_id(ctx, &parent_tree, &commit.tree()?)
+}
+
+fn commits_since(ctx: &Ctx, fork_id: Oid, tip_id: Oid) -> Maybe<Vec<Commit<'_>>> {
+    let mut walk = ctx.repo.revwalk()?;
+    walk.push(tip_id)?;
+    walk.hide(fork_id)?;
+    let mut commits = vec![];
+    for id in walk {
+        let commit = ctx.repo.find_commit(id?)?;
+        if commit.parent_count() == 1 {
+            commits.push(commit);
+        }
+    }
+    Ok(commits)
+}
+
+fn is_applied_to_main(
+    ctx: &Ctx,
+    main_commit: &Commit,
+    branch_commit: &Commit,
+    fork_id: Oid,
+) -> Maybe<bool> {
+    let fork_tree = ctx.repo.find_commit(fork_id)?.tree()?;
+    let main_tree = main_commit.tree()?;
+    let branch_tree = branch_commit.tree()?;
 
-    let main_id = ctx
+    let mut merged = ctx
+        .repo
+        .merge_trees(&fork_tree, &main_tree, &branch_tree, None)?;
+    if !merged.has_conflicts() && merged.write_tree_to(&ctx.repo)? == main_tree.id() {
+        return Ok(true);
+    }
+
+    let main_patch_ids = commits_since(ctx, fork_id, main_commit.id())?
+        .iter()
+        .map(|c| commit_patch_id(ctx, c))
+        .collect::<Maybe<HashSet<Oid>>>()?;
+
+    if main_patch_ids.is_empty() {
+        return Ok(false);
+    }
+
+    if main_patch_ids.contains(&patch_id(ctx, &fork_tree, &branch_tree)?) {
+        return Ok(true);
+    }
+
+    for commit in commits_since(ctx, fork_id, branch_commit.id())? {
+        if !main_patch_ids.contains(&commit_patch_id(ctx, &commit)?) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn find_prunable(ctx: &Ctx) -> Maybe<Vec<(String, PruneReason)>> {
+    let mut prunable = vec![];
+
+    let main_commit = ctx
         .repo
         .find_branch("main", git2::BranchType::Local)?
         .into_reference()
-        .peel_to_commit()?
-        .id();
-
-    save_temp(ctx, "Save before prune".into())?;
+        .peel_to_commit()?;
 
     for branch in ctx.repo.branches(Some(git2::BranchType::Local))?.flatten() {
         if let (branch, BranchType::Local) = branch {
@@ -32,31 +113,172 @@ pub fn prune_command(ctx: &Ctx) -> Attempt {
                 .into_reference()
                 .peel_to_commit()?;
 
-            let fork_id = ctx.repo.merge_base(main_id, branch_commit.id())?;
+            let fork_id = ctx.repo.merge_base(main_commit.id(), branch_commit.id())?;
 
             let branch_tree_id = branch_commit.tree_id();
             let fork_tree_id = ctx.repo.find_commit(fork_id)?.tree_id();
 
             if branch_tree_id == fork_tree_id {
-                branches_to_delete.push(name.into());
+                prunable.push((name.into(), PruneReason::Empty));
+            } else if is_applied_to_main(ctx, &main_commit, &branch_commit, fork_id)? {
+                prunable.push((name.into(), PruneReason::Merged));
+            }
+        }
+    }
+
+    Ok(prunable)
+}
+
+fn resolve_max_age(ctx: &Ctx, args: &PruneArgs) -> Maybe<Option<u64>> {
+    if let Some(days) = args.max_age {
+        return Ok(Some(days));
+    }
+    Ok(get_config_i64(ctx, "itch.archive.maxAge")?.and_then(|days| u64::try_from(days).ok()))
+}
+
+fn confirm(ctx: &Ctx, args: &PruneArgs, prompt: &str) -> bool {
+    if args.yes || !ctx.can_prompt() {
+        return true;
+    }
+    ask_option(prompt, &["yes", "no"], Some("no")) == "yes"
+}
+
+fn prune_remote(ctx: &Ctx, args: &PruneArgs) -> Attempt {
+    let prefix = get_remote_prefix()?;
+    let now = now_timestamp()?;
+    let max_age = resolve_max_age(ctx, args)?;
+
+    let is_expired = |tag: &ArchiveTag| {
+        max_age.is_some_and(|days| now.saturating_sub(tag.timestamp) > days * SECONDS_PER_DAY)
+    };
+
+    if prefix.is_empty() {
+        show_warning(
+            ctx,
+            "No remote prefix is set, so remote branches cannot be told apart from others.",
+        );
+    }
+
+    let mut remote_refs: Vec<String> = vec![];
+
+    for (name, _) in list_remote_refs(ctx)? {
+        if name.ends_with("^{}") {
+            continue;
+        }
+        if let Some(branch) = name.strip_prefix("refs/heads/") {
+            if let Some(branch) = branch.strip_prefix(&prefix)
+                && !prefix.is_empty()
+                && !local_branch_exists(ctx, branch)?
+            {
+                remote_refs.push(name.clone());
             }
+        } else if let Some(tag) = name
+            .strip_prefix("refs/tags/")
+            .and_then(|t| t.strip_prefix(&prefix))
+            .and_then(parse_archive_tag)
+            && is_expired(&tag)
+        {
+            remote_refs.push(name.clone());
+        }
+    }
+
+    let local_tags: Vec<ArchiveTag> = list_local_archive_tags(ctx)?
+        .into_iter()
+        .filter(is_expired)
+        .collect();
+
+    if remote_refs.is_empty() && local_tags.is_empty() {
+        if ctx.can_prompt() {
+            eprintln!("Nothing to prune.");
+        }
+        return Ok(());
+    }
+
+    if ctx.can_prompt() {
+        for name in &remote_refs {
+            eprintln!("  remote: {name}");
+        }
+        for tag in &local_tags {
+            eprintln!("  local: {} (archive of {})", tag.tag, tag.branch);
         }
     }
 
+    if !confirm(ctx, args, "Delete these refs?") {
+        return Ok(());
+    }
+
+    delete_remote_refs(ctx, &remote_refs)?;
+
+    for tag in &local_tags {
+        ctx.repo.tag_delete(&tag.tag)?;
+    }
+
+    if ctx.can_prompt() {
+        eprintln!("Deleted {} refs.", remote_refs.len() + local_tags.len());
+    }
+
+    Ok(())
+}
+
+pub fn prune_command(ctx: &Ctx, args: &PruneArgs) -> Attempt {
+    if args.remote {
+        return prune_remote(ctx, args);
+    }
+
+    save_temp(ctx, "Save before prune".into())?;
+
+    let prunable = find_prunable(ctx);
+
     pop_and_reset(ctx)?;
 
-    if branches_to_delete.is_empty() {
+    let prunable = prunable?;
+
+    if prunable.is_empty() {
         return Ok(());
     }
 
+    let mut archive = args.archive;
+
+    if ctx.can_prompt() {
+        for (name, reason) in &prunable {
+            match reason {
+                PruneReason::Empty => eprintln!("  {name} (no changes)"),
+                PruneReason::Merged => eprintln!("  {name} (already in main)"),
+            }
+        }
+
+        if !args.yes {
+            let default = if archive { "archive" } else { "delete" };
+            match ask_option(
+                "Prune these branches?",
+                &["delete", "archive", "cancel"],
+                Some(default),
+            )
+            .as_str()
+            {
+                "delete" => archive = false,
+                "archive" => archive = true,
+                _ => return Ok(()),
+            }
+        }
+    }
+
     let delete_args = DeleteArgs {
-        names: branches_to_delete,
+        names: prunable.into_iter().map(|(name, _)| name).collect(),
     };
 
-    delete_command(ctx, &delete_args)?;
+    if archive {
+        archive_command(ctx, &delete_args)?;
+    } else {
+        delete_command(ctx, &delete_args)?;
+    }
 
     if ctx.can_prompt() {
-        eprintln!("Deleted: {}", delete_args.names.join(", "));
+        eprintln!(
+            "{}: {}",
+            if archive { "Archived" } else { "Deleted" },
+            delete_args.names.join(", ")
+        );
     }
 
     Ok(())
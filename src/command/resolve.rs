@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use crate::{
+    branch::get_current_branch,
+    cli::ResolveArgs,
+    ctx::Ctx,
+    deferred::{clear_deferred, unresolved_in_workdir},
+    diff::{decode_text, has_conflict_markers},
+    editor::edit_file,
+    error::{Attempt, fail},
+    prompt::ask_option,
+    rerere::record_resolution,
+};
+
+fn resolve_file(ctx: &Ctx, branch: &str, file: &str) -> Attempt {
+    let Some(workdir) = ctx.repo.workdir() else {
+        return fail!("Cannot resolve conflicts in a bare repository.");
+    };
+    let path = workdir.join(file);
+    let preimage = decode_text(&std::fs::read(&path)?);
+
+    loop {
+        edit_file(&path)?;
+
+        let resolved = decode_text(&std::fs::read(&path)?);
+        if !has_conflict_markers(resolved.as_bytes()) {
+            record_resolution(ctx, &preimage, &resolved)?;
+            clear_deferred(ctx, branch, file)?;
+            eprintln!("Resolved {file}");
+            return Ok(());
+        }
+
+        match ask_option(
+            &format!("{file} still has conflict markers. What would you like to do?"),
+            &["edit", "done", "skip"],
+            Some("edit"),
+        )
+        .as_str()
+        {
+            "edit" => {}
+            "done" => {
+                clear_deferred(ctx, branch, file)?;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        }
+    }
+}
+
+pub fn resolve_command(ctx: &Ctx, args: &ResolveArgs) -> Attempt {
+    let branch = get_current_branch(ctx)?;
+
+    let mut unresolved = unresolved_in_workdir(ctx, &branch)?;
+
+    if !args.paths.is_empty() {
+        unresolved.retain(|file| {
+            args.paths
+                .iter()
+                .any(|p| Path::new(file).starts_with(Path::new(p)))
+        });
+    }
+
+    if args.done {
+        for file in &unresolved {
+            clear_deferred(ctx, &branch, file)?;
+        }
+        return Ok(());
+    }
+
+    if args.list || !ctx.can_prompt() {
+        for file in &unresolved {
+            println!("{file}");
+        }
+        return Ok(());
+    }
+
+    if unresolved.is_empty() {
+        eprintln!("No conflicts left to resolve.");
+        return Ok(());
+    }
+
+    for file in &unresolved {
+        resolve_file(ctx, &branch, file)?;
+    }
+
+    Ok(())
+}
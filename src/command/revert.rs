@@ -6,9 +6,14 @@ use crate::{
     error::{Attempt, fail},
     reset::pop_and_reset,
     save::save_temp,
+    tree::{changed_paths, copy_paths},
 };
 
 pub fn revert_command(ctx: &Ctx, args: &RevertArgs) -> Attempt {
+    if args.args.is_empty() {
+        return fail!("Missing files to revert.");
+    }
+
     save_temp(ctx, "Save before revert".to_string())?;
 
     let head_commit = ctx.repo.head()?.peel_to_commit()?;
@@ -22,17 +27,27 @@ pub fn revert_command(ctx: &Ctx, args: &RevertArgs) -> Attempt {
         .repo
         .find_commit(ctx.repo.merge_base(base_commit.id(), head_commit.id())?)?;
 
-    if args.args.is_empty() {
-        return fail!("Missing files to revert.");
+    let fork_tree = fork_commit.tree()?;
+    let head_tree = head_commit.tree()?;
+    let paths = changed_paths(&ctx.repo, &fork_tree, &head_tree, &args.args)?;
+    if paths.is_empty() {
+        pop_and_reset(ctx)?;
+        return fail!(format!(
+            "No changes to {} since the last merge.",
+            args.args.join(", ")
+        ));
     }
-    let tree = fork_commit.tree()?;
+
+    let tree = ctx
+        .repo
+        .find_tree(copy_paths(&ctx.repo, &paths, &fork_tree, &head_tree)?)?;
     let mut options = CheckoutBuilder::new();
 
     options.force();
-    options.remove_untracked(true);
+    options.disable_pathspec_match(true);
 
-    for file in &args.args {
-        options.path(file);
+    for path in &paths {
+        options.path(path);
     }
     ctx.repo
         .checkout_tree(&tree.into_object(), Some(&mut options))?;
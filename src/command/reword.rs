@@ -0,0 +1,70 @@
+use crate::{
+    cli::RewordArgs,
+    commit::{find_save, short_summary},
+    ctx::Ctx,
+    editor::edit_temp_text,
+    error::{Attempt, Maybe, fail},
+    history::{HistoryStep, StepAction},
+    save::{include_footer, resolve_commit_message, strip_footer},
+};
+
+use super::history::{branch_saves, rewrite_history};
+
+const REWORD_HELP: &str = "
+# Write the new message for this save. Lines starting with # are ignored,
+# and an empty message cancels. The itch.footer trailer is added back automatically.
+";
+
+fn edit_message(ctx: &Ctx, message: &str) -> Maybe<String> {
+    let text = edit_temp_text(
+        &format!("{}\n{REWORD_HELP}", strip_footer(ctx, message)?),
+        None,
+    )?;
+    let lines: Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).collect();
+    Ok(lines.join("\n").trim().to_string())
+}
+
+pub fn reword_command(ctx: &Ctx, args: &RewordArgs) -> Attempt {
+    let mut saves = branch_saves(ctx)?;
+    saves.reverse();
+
+    let (query, message) = match args.args.split_first() {
+        Some((query, message)) => (query.as_str(), message),
+        None => ("1", &[][..]),
+    };
+    let save = find_save(&saves, query)?;
+    let original = save.message().unwrap_or_default();
+
+    let message = match resolve_commit_message(message) {
+        Some(message) => message,
+        None => edit_message(ctx, original)?,
+    };
+    if message.is_empty() {
+        return fail!("Empty message, nothing was changed.");
+    }
+    let message = include_footer(ctx, &strip_footer(ctx, &message)?)?;
+    if message == original {
+        return Ok(());
+    }
+
+    let steps: Vec<HistoryStep> = saves
+        .iter()
+        .rev()
+        .map(|c| HistoryStep {
+            id: c.id(),
+            action: if c.id() == save.id() {
+                StepAction::Reword(message.clone())
+            } else {
+                StepAction::Pick
+            },
+        })
+        .collect();
+    let label = short_summary(save);
+    rewrite_history(ctx, &steps)?;
+
+    if ctx.can_prompt() {
+        eprintln!("Reworded {label}");
+    }
+
+    Ok(())
+}
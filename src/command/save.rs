@@ -1,12 +1,29 @@
 use crate::{
-    branch::get_current_branch, cli::SaveArgs, ctx::Ctx, error::Attempt, remote::try_push_branch,
-    reset::reset_repo, save::save,
+    branch::get_current_branch,
+    cli::SaveArgs,
+    ctx::Ctx,
+    deferred::unresolved_in_workdir,
+    error::{Attempt, fail},
+    remote::try_push_branch,
+    reset::reset_repo,
+    save::save,
 };
 
 pub fn save_command(ctx: &Ctx, args: &SaveArgs, silent: bool) -> Attempt {
-    save(ctx, args, silent)?;
     let branch_name = get_current_branch(ctx)?;
 
+    if !args.allow_conflicts {
+        let unresolved = unresolved_in_workdir(ctx, &branch_name)?;
+        if !unresolved.is_empty() {
+            return fail!(format!(
+                "Conflict markers remain in {}. Run `itch resolve` or pass --allow-conflicts.",
+                unresolved.join(", ")
+            ));
+        }
+    }
+
+    save(ctx, args, silent)?;
+
     try_push_branch(ctx, &branch_name);
 
     reset_repo(ctx)?;
@@ -1,16 +1,127 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use git2::{Commit, Oid, ResetType, Sort};
+
 use crate::{
-    branch::choose_random_branch_name,
+    branch::{choose_random_branch_name, get_current_branch, local_branch_exists},
     cli::{LoadArgs, SplitArgs},
     ctx::Ctx,
-    error::Attempt,
-    save::save_temp,
+    error::{Attempt, Maybe, fail},
+    reset::pop_and_reset,
+    save::{include_footer, save_temp},
+    tree::{changed_paths, copy_paths},
 };
 
 use super::load::load_command;
 
-pub fn split_command(ctx: &Ctx, args: &SplitArgs) -> Attempt {
+fn remove_paths<'a>(
+    ctx: &'a Ctx,
+    paths: &[PathBuf],
+    fork: &Commit,
+    main: &Commit,
+    head: &Commit<'a>,
+) -> Maybe<Commit<'a>> {
+    let repo = &ctx.repo;
+    let fork_tree = fork.tree()?;
+
+    let mut walk = repo.revwalk()?;
+    walk.push(head.id())?;
+    walk.hide(main.id())?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+    let mut rewritten: HashMap<Oid, Oid> = HashMap::new();
+    for id in walk {
+        let commit = repo.find_commit(id?)?;
+        let tree = repo.find_tree(copy_paths(repo, paths, &fork_tree, &commit.tree()?)?)?;
+
+        let parents = commit
+            .parent_ids()
+            .map(|id| repo.find_commit(*rewritten.get(&id).unwrap_or(&id)))
+            .collect::<Result<Vec<Commit>, _>>()?;
+
+        if let [parent] = parents.as_slice()
+            && parent.tree_id() == tree.id()
+            && commit.parent(0)?.tree_id() != commit.tree_id()
+        {
+            rewritten.insert(commit.id(), parent.id());
+            continue;
+        }
+
+        let parent_refs: Vec<&Commit> = parents.iter().collect();
+        let new_id = repo.commit(
+            None,
+            &commit.author(),
+            &commit.committer(),
+            commit.message().unwrap_or(""),
+            &tree,
+            &parent_refs,
+        )?;
+        rewritten.insert(commit.id(), new_id);
+    }
+
+    Ok(repo.find_commit(*rewritten.get(&head.id()).unwrap_or(&head.id()))?)
+}
+
+fn split_files(ctx: &Ctx, name: &str, files: &[String]) -> Attempt {
+    let repo = &ctx.repo;
+    let branch_name = get_current_branch(ctx)?;
+    let main = repo
+        .find_branch("main", git2::BranchType::Local)?
+        .into_reference()
+        .peel_to_commit()?;
+
+    if local_branch_exists(ctx, name)? {
+        return fail!(format!(
+            "A branch named {name} already exists. Pick another name."
+        ));
+    }
+
     save_temp(ctx, "Save before split".to_string())?;
 
+    let result = (|| -> Maybe<usize> {
+        let head = repo.head()?.peel_to_commit()?;
+        let fork = repo.find_commit(repo.merge_base(main.id(), head.id())?)?;
+        let fork_tree = fork.tree()?;
+
+        let paths = changed_paths(repo, &fork_tree, &head.tree()?, files)?;
+        if paths.is_empty() {
+            return fail!(format!(
+                "No changes to {} on this branch.",
+                files.join(", ")
+            ));
+        }
+
+        let tree = repo.find_tree(copy_paths(repo, &paths, &head.tree()?, &fork_tree)?)?;
+        let signature = repo.signature()?;
+        let split_commit = repo.find_commit(repo.commit(
+            None,
+            &signature,
+            &signature,
+            &include_footer(ctx, &format!("Split from {branch_name}"))?,
+            &tree,
+            &[&fork],
+        )?)?;
+
+        let remaining = remove_paths(ctx, &paths, &fork, &main, &head)?;
+        repo.branch(name, &split_commit, false)?;
+        repo.reset(remaining.as_object(), ResetType::Hard, None)?;
+        Ok(paths.len())
+    })();
+
+    pop_and_reset(ctx)?;
+    let count = result?;
+
+    if ctx.can_prompt() {
+        eprintln!(
+            "Split {count} {} to {name}",
+            if count == 1 { "file" } else { "files" }
+        );
+    }
+
+    Ok(())
+}
+
+pub fn split_command(ctx: &Ctx, args: &SplitArgs) -> Attempt {
     let name: String = match &args.name {
         Some(n) => {
             if n.is_empty() {
@@ -22,6 +133,12 @@ pub fn split_command(ctx: &Ctx, args: &SplitArgs) -> Attempt {
         None => choose_random_branch_name(ctx),
     }?;
 
+    if !args.files.is_empty() {
+        return split_files(ctx, &name, &args.files);
+    }
+
+    save_temp(ctx, "Save before split".to_string())?;
+
     let head_commit = ctx.repo.head()?.peel_to_commit()?;
 
     ctx.repo.branch(&name, &head_commit, false)?;
@@ -1,15 +1,19 @@
-use std::{rc::Rc, vec};
+use std::vec;
 
-use git2::{Commit, Delta, DiffDelta, DiffFile, StatusOptions};
+use git2::{Delta, DiffDelta, DiffFile, StatusOptions};
 
 use crate::{
     cli::StatusArgs,
+    commit::count_commits_since,
     ctx::Ctx,
     diff::{collapse_renames, good_diff_options},
-    error::{Attempt, Maybe, fail},
+    error::{Attempt, Maybe},
     reset::reset_repo,
+    sync::conflict_badge,
 };
 
+use super::sync::sync_conflict_count;
+
 #[derive(Debug)]
 pub struct BranchSummary {
     pub name: String,
@@ -23,6 +27,7 @@ pub struct ForkInfo {
     pub head: BranchSummary,
     pub dirty: bool,
     pub file_statuses: Vec<SegmentedStatus>,
+    pub conflicting_files: usize,
 }
 
 struct Styles {
@@ -236,7 +241,7 @@ fn get_post_fork_commits(info: &BranchSummary, styles: &Styles) -> String {
     match info.commit_count {
         0 => String::new(),
         1 => wrapped_message,
-        2 => format!("{}o{} ─ {wrapped_message}", styles.highlight, styles.muted,),
+        2 => format!("{}o{} ─ {wrapped_message}", styles.highlight, styles.muted),
         3 => format!(
             "{}o{} ─ {}o{} ─ {wrapped_message}",
             styles.highlight, styles.muted, styles.highlight, styles.muted,
@@ -280,29 +285,16 @@ fn draw_fork_diagram(info: &ForkInfo, styles: &Styles) {
         main_dirty_indicator = "*";
     }
 
+    let badge = conflict_badge(info.conflicting_files)
+        .map(|b| format!(" {}({b}){}", styles.muted, styles.end))
+        .unwrap_or_default();
+
     println!(
-        "{}─ {}o{} ─ {}{base_display} {}←{} {base_name}{main_dirty_indicator}",
+        "{}─ {}o{} ─ {}{base_display} {}←{} {base_name}{main_dirty_indicator}{badge}",
         styles.muted, styles.highlight, styles.muted, styles.end, styles.muted, styles.end
     );
 }
 
-fn count_commits_since(_ctx: &Ctx, older: &Commit, newer: &Commit) -> Maybe<usize> {
-    let mut count: usize = 0;
-    let mut current = Rc::from(newer.clone());
-    while current.id() != older.id() {
-        let next = current.parents().next();
-        match next {
-            Some(c) => {
-                count += 1;
-                current = Rc::from(c);
-            }
-            None => return fail!("Unable to navigate to fork point."),
-        }
-    }
-
-    Ok(count)
-}
-
 /*
 
 On branch: example
@@ -343,6 +335,11 @@ pub fn resolve_fork_info(ctx: &Ctx, branch_name: Option<&str>) -> Maybe<ForkInfo
 
     let base_past_fork = count_commits_since(ctx, &fork_point, &base_commit)?;
     let head_past_fork = count_commits_since(ctx, &fork_point, &head_commit)?;
+    let conflicting_files = if base_past_fork > 0 && head_past_fork > 0 {
+        sync_conflict_count(ctx, head_name)
+    } else {
+        0
+    };
 
     let mut options = good_diff_options();
 
@@ -411,6 +408,7 @@ pub fn resolve_fork_info(ctx: &Ctx, branch_name: Option<&str>) -> Maybe<ForkInfo
         },
         dirty: head_dirty,
         file_statuses: statuses,
+        conflicting_files,
     })
 }
 
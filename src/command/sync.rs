@@ -1,28 +1,44 @@
 use std::{
     ops::BitAnd,
     path::{Path, PathBuf},
+    process::Command,
 };
 
 use git2::{
-    ErrorCode, Index, IndexConflict, IndexEntry, Oid, RebaseOperationType, RebaseOptions,
-    Repository,
+    Commit, ErrorCode, Index, IndexConflict, IndexEntry, MergeOptions, Oid, RebaseOperationType,
+    RebaseOptions, Repository,
 };
 
 use crate::{
     branch::get_current_branch,
-    cli::SyncArgs,
+    cli::{MergeCommits, SyncArgs, SyncMode},
+    config::{get_config_bool, get_config_enum},
     ctx::Ctx,
-    diff::get_merge_text,
+    deferred::{record_deferred, unresolved_in_workdir},
+    diff::{
+        ConflictMarkers, ConflictStyle, decode_text, detect_encoding, encode_text,
+        get_clean_merge_text, get_merge_bytes, get_merge_text, get_union_text, is_mergeable_text,
+        similarity,
+    },
     editor::edit_temp_text,
-    error::{Attempt, Maybe, fail},
+    error::{Attempt, Maybe, fail, inner_fail},
+    merge_driver::{run_driver, write_versions},
     path::bytes2path,
+    print::show_warning,
     prompt::ask_option,
     remote::try_pull_main,
-    reset::pop_and_reset,
+    rerere::{record_resolution, replay_resolution},
+    reset::{pop_and_reset, skip_temp_commits},
+    rules::{ConflictRule, RuleAction, find_rule, load_rules},
     save::save_temp,
-    sync::{Conflict, MergeConflict, ResolutionChoice, ResolutionMap, SyncDetails},
+    sync::{
+        Conflict, ConflictPath, ConflictPreview, MergeConflict, ResolutionChoice, ResolutionMap,
+        SyncDetails, SyncOptions, SyncReport, conflict_badge, count_conflicting_files,
+    },
 };
 
+use super::squash::resolve_squashed_message;
+
 fn delete_entry(index: &mut Index, path: &Path) -> Attempt {
     index.remove_path(path).map_err(std::convert::Into::into)
 }
@@ -51,26 +67,284 @@ fn select_entry(index: &mut Index, old_path: &Path, entry: &IndexEntry) -> Attem
     index.add(&new_entry).map_err(std::convert::Into::into)
 }
 
+const RENAME_THRESHOLD: u32 = 50;
+
+fn target_entry(conflict: &IndexConflict) -> Maybe<&IndexEntry> {
+    let ancestor_path = conflict.ancestor.as_ref().map(|e| &e.path);
+    match (conflict.our.as_ref(), conflict.their.as_ref()) {
+        (_, Some(their)) if Some(&their.path) != ancestor_path => Ok(their),
+        (Some(our), _) => Ok(our),
+        (None, Some(their)) => Ok(their),
+        (None, None) => conflict
+            .ancestor
+            .as_ref()
+            .ok_or_else(|| inner_fail!("Conflict has no paths.")),
+    }
+}
+
 fn extract_path(conflict: &IndexConflict) -> Maybe<PathBuf> {
-    if let Some(our) = conflict.our.as_ref() {
-        bytes2path(&our.path)
-    } else {
-        bytes2path(&conflict.ancestor.as_ref().unwrap().path)
+    bytes2path(&target_entry(conflict)?.path)
+}
+
+fn conflict_path(env: &SyncEnv, conflict: &IndexConflict) -> Maybe<ConflictPath> {
+    let path: String = extract_path(conflict)?.to_string_lossy().into();
+    let name = |entry: Option<&IndexEntry>| -> Maybe<Option<String>> {
+        entry
+            .map(|e| Ok(bytes2path(&e.path)?.to_string_lossy().into()))
+            .transpose()
+    };
+    let ancestor = name(conflict.ancestor.as_ref())?;
+
+    let mut notes = vec![];
+    for (side, label) in [
+        (name(conflict.their.as_ref())?, env.incoming_name),
+        (name(conflict.our.as_ref())?, env.upstream_name),
+    ] {
+        let Some(side) = side else {
+            continue;
+        };
+        match &ancestor {
+            Some(ancestor) if side == path && *ancestor != path => {
+                notes.push(format!("renamed from {ancestor} on {label}"));
+            }
+            _ if side != path && ancestor.as_ref() != Some(&side) => {
+                notes.push(format!("{side} on {label}"));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(ConflictPath {
+        path,
+        note: (!notes.is_empty()).then(|| notes.join(", ")),
+    })
+}
+
+fn take_rename(
+    repo: &Repository,
+    added: &mut Vec<IndexConflict>,
+    ancestor: &IndexEntry,
+    side: fn(&IndexConflict) -> Option<&IndexEntry>,
+) -> Maybe<Option<IndexEntry>> {
+    let mut best: Option<(usize, usize)> = None;
+    for (i, conflict) in added.iter().enumerate() {
+        let Some(entry) = side(conflict) else {
+            continue;
+        };
+        let score = similarity(repo, &ancestor.id, &entry.id)?;
+        if score >= RENAME_THRESHOLD as usize && best.is_none_or(|(_, s)| score > s) {
+            best = Some((i, score));
+        }
+    }
+
+    Ok(best.and_then(|(i, _)| side(&added.remove(i)).map(clone_entry)))
+}
+
+fn pair_renames(
+    repo: &Repository,
+    index: &mut Index,
+    conflicts: Vec<IndexConflict>,
+) -> Maybe<Vec<IndexConflict>> {
+    let (mut added, mut conflicts): (Vec<IndexConflict>, Vec<IndexConflict>) = conflicts
+        .into_iter()
+        .partition(|c| c.ancestor.is_none() && (c.our.is_none() || c.their.is_none()));
+
+    for conflict in &mut conflicts {
+        let Some(ancestor) = conflict.ancestor.as_ref() else {
+            continue;
+        };
+
+        let our = match conflict.our {
+            None => take_rename(repo, &mut added, ancestor, |c| c.our.as_ref())?,
+            Some(_) => None,
+        };
+        let their = match conflict.their {
+            None => take_rename(repo, &mut added, ancestor, |c| c.their.as_ref())?,
+            Some(_) => None,
+        };
+
+        for entry in [&our, &their].into_iter().flatten() {
+            index.conflict_remove(&bytes2path(&entry.path)?)?;
+        }
+        if our.is_some() || their.is_some() {
+            index.conflict_remove(&bytes2path(&ancestor.path)?)?;
+        }
+
+        conflict.our = conflict.our.take().or(our);
+        conflict.their = conflict.their.take().or(their);
     }
+
+    conflicts.extend(added);
+    Ok(conflicts)
 }
 
 fn get_entry_oid(entry: Option<&IndexEntry>) -> Oid {
     entry.as_ref().map_or_else(Oid::zero, |e| e.id)
 }
 
+fn text_sides(repo: &Repository, conflict: &IndexConflict) -> Maybe<Option<(Oid, Oid, Oid)>> {
+    let (Some(main_entry), Some(branch_entry)) = (conflict.our.as_ref(), conflict.their.as_ref())
+    else {
+        return Ok(None);
+    };
+
+    let ancestor_oid = get_entry_oid(conflict.ancestor.as_ref());
+
+    for oid in [ancestor_oid, main_entry.id, branch_entry.id] {
+        if !oid.is_zero() && !is_mergeable_text(repo.find_blob(oid)?.content()) {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some((ancestor_oid, main_entry.id, branch_entry.id)))
+}
+
+fn is_renamed(conflict: &IndexConflict) -> bool {
+    let (Some(ancestor), Some(our), Some(their)) =
+        (&conflict.ancestor, &conflict.our, &conflict.their)
+    else {
+        return false;
+    };
+    our.path != ancestor.path || their.path != ancestor.path
+}
+
+fn merge_renamed(repo: &Repository, conflict: &IndexConflict) -> Maybe<Option<String>> {
+    if !is_renamed(conflict) {
+        return Ok(None);
+    }
+    match text_sides(repo, conflict)? {
+        Some((ancestor, main, branch)) => get_clean_merge_text(repo, &ancestor, &main, &branch),
+        None => Ok(None),
+    }
+}
+
+struct SyncEnv<'a> {
+    ctx: &'a Ctx,
+    branch_name: &'a str,
+    incoming_name: &'a str,
+    upstream_name: &'a str,
+    options: &'a SyncOptions<'a>,
+    rules: &'a [ConflictRule],
+    markers: ConflictMarkers,
+    builtin_only: bool,
+}
+
+fn short_id(id: Oid) -> String {
+    id.to_string()[..7].to_string()
+}
+
+fn conflict_preimage(repo: &Repository, conflict: &IndexConflict) -> Maybe<Option<String>> {
+    match text_sides(repo, conflict)? {
+        Some((ancestor, main, branch)) => {
+            get_merge_text(repo, &ancestor, &main, &branch, &ConflictMarkers::default()).map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+fn run_rule_command(
+    ctx: &Ctx,
+    path: &str,
+    sides: (Oid, Oid, Oid),
+    command: &str,
+) -> Maybe<Option<String>> {
+    let dir = tempfile::tempdir()?;
+    let [base, main, branch] = write_versions(&ctx.repo, dir.path(), sides)?;
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(ctx.repo.workdir().unwrap_or(dir.path()))
+        .env("ITCH_PATH", path)
+        .env("ITCH_BASE", &base)
+        .env("ITCH_MAIN", &main)
+        .env("ITCH_BRANCH", &branch)
+        .output()
+        .map_err(|_| inner_fail!("Failed to start conflict rule command."))?;
+
+    if !output.status.success() {
+        show_warning(
+            ctx,
+            &format!("Conflict rule command failed for {path}: {command}"),
+        );
+        return Ok(None);
+    }
+
+    Ok(Some(decode_text(&output.stdout)))
+}
+
+fn rule_resolution(
+    ctx: &Ctx,
+    path: &str,
+    conflict: &IndexConflict,
+    rule: &ConflictRule,
+) -> Maybe<Option<ResolutionChoice>> {
+    let content = match &rule.action {
+        RuleAction::Keep => return Ok(Some(ResolutionChoice::Incoming)),
+        RuleAction::Reset => return Ok(Some(ResolutionChoice::Base)),
+        RuleAction::Union => match text_sides(&ctx.repo, conflict)? {
+            Some((ancestor, main, branch)) => {
+                Some(get_union_text(&ctx.repo, &ancestor, &main, &branch)?)
+            }
+            None => None,
+        },
+        RuleAction::Run(_)
+            if !get_config_bool(ctx, "itch.conflicts.allowRun")?.unwrap_or(false) =>
+        {
+            show_warning(
+                ctx,
+                &format!(
+                    "Ignoring conflict rule command for {path}. Set itch.conflicts.allowRun to run it."
+                ),
+            );
+            return Ok(None);
+        }
+        RuleAction::Run(command) => match text_sides(&ctx.repo, conflict)? {
+            Some(sides) => run_rule_command(ctx, path, sides, command)?,
+            None => None,
+        },
+    };
+    Ok(content.map(ResolutionChoice::Manual))
+}
+
+fn remember_resolution(
+    ctx: &Ctx,
+    conflict: &IndexConflict,
+    resolution: &ResolutionChoice,
+) -> Attempt {
+    let Some(preimage) = conflict_preimage(&ctx.repo, conflict)? else {
+        return Ok(());
+    };
+
+    let resolved = match (resolution, conflict.our.as_ref(), conflict.their.as_ref()) {
+        (ResolutionChoice::Incoming, _, Some(entry)) | (ResolutionChoice::Base, Some(entry), _) => {
+            decode_text(ctx.repo.find_blob(entry.id)?.content())
+        }
+        (ResolutionChoice::Manual(content), _, _) => content.clone(),
+        _ => return Ok(()),
+    };
+
+    record_resolution(ctx, &preimage, &resolved)
+}
+
+fn branch_entry(conflict: &IndexConflict) -> Maybe<&IndexEntry> {
+    conflict
+        .their
+        .as_ref()
+        .or(conflict.our.as_ref())
+        .ok_or_else(|| inner_fail!("Conflict has no version to resolve into."))
+}
+
 fn apply_resolution(
-    repo: &Repository,
+    env: &SyncEnv,
     index: &mut Index,
     conflict: &IndexConflict,
     resolution: &ResolutionChoice,
 ) -> Attempt {
     type C = ResolutionChoice;
 
+    let repo = &env.ctx.repo;
+
     let current_path = extract_path(conflict)?;
 
     match (resolution, conflict.our.as_ref(), conflict.their.as_ref()) {
@@ -82,68 +356,159 @@ fn apply_resolution(
             let ancestor_oid = get_entry_oid(conflict.ancestor.as_ref());
             let our_oid = get_entry_oid(conflict.our.as_ref());
             let their_oid = get_entry_oid(conflict.their.as_ref());
-            let conflicted = get_merge_text(repo, &ancestor_oid, &their_oid, &our_oid)?;
-            let mut new_entry = clone_entry(conflict.their.as_ref().unwrap());
-            new_entry.id = repo.blob(conflicted.as_bytes())?;
-            select_entry(index, &current_path, &new_entry)
+            let conflicted =
+                get_merge_bytes(repo, &ancestor_oid, &our_oid, &their_oid, &env.markers)?;
+            let mut new_entry = clone_entry(branch_entry(conflict)?);
+            new_entry.path.clone_from(&target_entry(conflict)?.path);
+            new_entry.id = repo.blob(&conflicted)?;
+            select_entry(index, &current_path, &new_entry)?;
+            record_deferred(env.ctx, env.branch_name, &current_path.to_string_lossy())
         }
         (C::Manual(str), _, _) => {
-            let mut new_entry = clone_entry(conflict.their.as_ref().unwrap());
-            new_entry.id = repo.blob(str.as_bytes())?;
+            let branch_entry = branch_entry(conflict)?;
+            let encoding = detect_encoding(repo.find_blob(branch_entry.id)?.content());
+            let mut new_entry = clone_entry(branch_entry);
+            new_entry.path.clone_from(&target_entry(conflict)?.path);
+            let content = encode_text(str, encoding)
+                .map_err(|e| inner_fail!(format!("Cannot save {}: {e}", current_path.display())))?;
+            new_entry.id = repo.blob(&content)?;
             select_entry(index, &current_path, &new_entry)
         }
     }
 }
 
+fn replay_recorded(env: &SyncEnv, index: &mut Index, conflict: &IndexConflict) -> Maybe<bool> {
+    let Some(preimage) = conflict_preimage(&env.ctx.repo, conflict)? else {
+        return Ok(false);
+    };
+    let Some(resolved) = replay_resolution(env.ctx, &preimage)? else {
+        return Ok(false);
+    };
+    apply_resolution(env, index, conflict, &ResolutionChoice::Manual(resolved))?;
+    Ok(true)
+}
+
+fn describe_merge_conflict(
+    env: &SyncEnv,
+    conflict: &IndexConflict,
+    path: ConflictPath,
+    main_entry: &IndexEntry,
+    branch_entry: &IndexEntry,
+) -> Maybe<Conflict> {
+    let repo = &env.ctx.repo;
+    let main_blob = repo.find_blob(main_entry.id)?;
+    let branch_blob = repo.find_blob(branch_entry.id)?;
+
+    let original_id = get_entry_oid(conflict.ancestor.as_ref());
+    let base_content = if original_id.is_zero() {
+        String::new()
+    } else {
+        decode_text(repo.find_blob(original_id)?.content())
+    };
+
+    match (
+        is_mergeable_text(main_blob.content()),
+        is_mergeable_text(branch_blob.content()),
+    ) {
+        (true, true) => Ok(Conflict::Merge(MergeConflict {
+            path,
+            main_content: decode_text(main_blob.content()),
+            branch_content: decode_text(branch_blob.content()),
+            base_content,
+            merge_content: get_merge_text(
+                repo,
+                &original_id,
+                &main_entry.id,
+                &branch_entry.id,
+                &env.markers,
+            )?,
+        })),
+        _ => Ok(Conflict::OpaqueMerge(path)),
+    }
+}
+
+fn resolve_automatically(
+    env: &SyncEnv,
+    index: &mut Index,
+    conflict: &IndexConflict,
+) -> Maybe<Option<String>> {
+    let ctx = env.ctx;
+    let path: String = extract_path(conflict)?.to_string_lossy().into();
+
+    if let Some(merged) = merge_renamed(&ctx.repo, conflict)? {
+        apply_resolution(env, index, conflict, &ResolutionChoice::Manual(merged))?;
+        return Ok(Some("merged across rename".to_string()));
+    }
+
+    if let Some(rule) = find_rule(env.rules, &path)
+        && !(env.builtin_only && matches!(rule.action, RuleAction::Run(_)))
+        && let Some(resolution) = rule_resolution(ctx, &path, conflict, rule)?
+    {
+        apply_resolution(env, index, conflict, &resolution)?;
+        return Ok(Some(format!("rule: {}", rule.describe())));
+    }
+
+    if !env.builtin_only
+        && let Some(sides) = text_sides(&ctx.repo, conflict)?
+        && let Some((driver, merged)) = run_driver(ctx, &path, sides)?
+    {
+        apply_resolution(env, index, conflict, &ResolutionChoice::Manual(merged))?;
+        return Ok(Some(format!("merge driver: {driver}")));
+    }
+
+    if replay_recorded(env, index, conflict)? {
+        return Ok(Some("recorded resolution".to_string()));
+    }
+
+    Ok(None)
+}
+
 fn resolve_conflict(
-    ctx: &Ctx,
+    env: &SyncEnv,
     index: &mut Index,
     conflict: &IndexConflict,
-    resolutions: Option<&ResolutionMap>,
+    auto_resolved: &mut Vec<String>,
 ) -> Maybe<Option<Conflict>> {
+    let ctx = env.ctx;
     let repo = &ctx.repo;
     let current_path = extract_path(conflict)?;
     let current_path_string: String = current_path.to_string_lossy().into();
 
-    if let Some(resolution) = resolutions.and_then(|f| f.get(&current_path_string)) {
-        apply_resolution(repo, index, conflict, resolution)?;
+    if let Some(resolution) = env
+        .options
+        .resolutions
+        .and_then(|f| f.get(&current_path_string))
+    {
+        remember_resolution(ctx, conflict, resolution)?;
+        apply_resolution(env, index, conflict, resolution)?;
+        return Ok(None);
+    }
+
+    if let Some(note) = resolve_automatically(env, index, conflict)? {
+        auto_resolved.push(format!("{current_path_string} ({note})"));
+        return Ok(None);
+    }
+
+    if let Some(resolution) = &env.options.default_resolution
+        && (!matches!(resolution, ResolutionChoice::Later) || text_sides(repo, conflict)?.is_some())
+    {
+        remember_resolution(ctx, conflict, resolution)?;
+        apply_resolution(env, index, conflict, resolution)?;
         return Ok(None);
     }
 
+    let can_prompt = ctx.can_prompt() && !env.options.no_prompt;
+
+    let label = conflict_path(env, conflict)?;
+
     let resolution = match (&conflict.their, &conflict.our) {
         (Some(branch_entry), Some(main_entry)) => {
-            if !ctx.can_prompt() {
-                let main_blob = repo.find_blob(main_entry.id)?;
-                let branch_blob = repo.find_blob(branch_entry.id)?;
-
-                let original_id = if let Some(ancestor_entry) = conflict.ancestor.as_ref() {
-                    ancestor_entry.id
-                } else {
-                    repo.blob("".as_bytes())?
-                };
-
-                match (main_blob.is_binary(), branch_blob.is_binary()) {
-                    (false, false) => {
-                        return Ok(Some(Conflict::Merge(MergeConflict {
-                            path: current_path_string,
-                            main_content: String::from_utf8_lossy(main_blob.content()).into(),
-                            branch_content: String::from_utf8_lossy(branch_blob.content()).into(),
-                            merge_content: get_merge_text(
-                                repo,
-                                &original_id,
-                                &main_entry.id,
-                                &branch_entry.id,
-                            )?,
-                        })));
-                    }
-                    _ => {
-                        return Ok(Some(Conflict::OpaqueMerge(current_path_string)));
-                    }
-                }
+            if !can_prompt {
+                return describe_merge_conflict(env, conflict, label, main_entry, branch_entry)
+                    .map(Some);
             }
 
-            let prompt =
-                format!("{current_path_string} is conflicted. What would you like to do?",);
+            let prompt = format!("{label} is conflicted. What would you like to do?");
 
             let options = ["keep", "reset", "later", "edit"];
 
@@ -154,12 +519,14 @@ fn resolve_conflict(
                 "edit" => {
                     let path = bytes2path(&branch_entry.path)?;
 
-                    let original_id = conflict
-                        .ancestor
-                        .as_ref()
-                        .map_or_else(|| repo.blob("".as_bytes()).unwrap(), |e| e.id);
-                    let patch_text =
-                        get_merge_text(&ctx.repo, &original_id, &main_entry.id, &branch_entry.id)?;
+                    let original_id = get_entry_oid(conflict.ancestor.as_ref());
+                    let patch_text = get_merge_text(
+                        repo,
+                        &original_id,
+                        &main_entry.id,
+                        &branch_entry.id,
+                        &env.markers,
+                    )?;
                     let edited_string = edit_temp_text(&patch_text, path.extension())?;
                     ResolutionChoice::Manual(edited_string)
                 }
@@ -167,15 +534,14 @@ fn resolve_conflict(
             }
         }
         // File deleted on main
-        (Some(branch_entry), None) => {
-            let current_path = bytes2path(&branch_entry.path)?;
-            if !ctx.can_prompt() {
-                return Ok(Some(Conflict::MainDeletion(current_path_string)));
+        (Some(_), None) => {
+            if !can_prompt {
+                return Ok(Some(Conflict::MainDeletion(label)));
             }
             match ask_option(
                 &format!(
-                    "{} was deleted on main. What would you like to do?",
-                    current_path.to_string_lossy(),
+                    "{label} was deleted on {}. What would you like to do?",
+                    env.upstream_name
                 ),
                 &["delete", "keep"],
                 Some("keep"),
@@ -189,13 +555,14 @@ fn resolve_conflict(
         }
         // File deleted on branch
         (None, Some(_)) => {
-            if !ctx.can_prompt() {
-                return Ok(Some(Conflict::BranchDeletion(current_path_string)));
+            if !can_prompt {
+                return Ok(Some(Conflict::BranchDeletion(label)));
             }
 
             match ask_option(
                 &format!(
-                    "{current_path_string} was deleted, but has been modified on main. What would you like to do?",
+                    "{label} was deleted, but has been modified on {}. What would you like to do?",
+                    env.upstream_name
                 ),
                 &["delete", "keep"],
                 Some("keep"),
@@ -207,91 +574,330 @@ fn resolve_conflict(
                 _ => panic!("Unhandled option"),
             }
         }
-        (None, None) => panic!("Expected either main or branch entry"),
+        // Removed on both sides, possibly under different names
+        (None, None) => ResolutionChoice::Base,
     };
 
-    apply_resolution(repo, index, conflict, &resolution)?;
+    remember_resolution(ctx, conflict, &resolution)?;
+    apply_resolution(env, index, conflict, &resolution)?;
 
     Ok(None)
 }
 
-pub fn try_sync_branch(
+fn resolve_index(
+    env: &SyncEnv,
+    index: &mut Index,
+    auto_resolved: &mut Vec<String>,
+) -> Maybe<Vec<Conflict>> {
+    let mut conflicts: Vec<IndexConflict> = vec![];
+
+    index.conflicts()?.try_for_each(|c| -> Attempt {
+        conflicts.push(c?);
+        Ok(())
+    })?;
+
+    let conflicts = pair_renames(&env.ctx.repo, index, conflicts)?;
+
+    if env.ctx.can_prompt() && !env.options.no_prompt {
+        eprintln!(
+            "\nThere are {} {} to resolve.",
+            conflicts.len(),
+            if conflicts.len() == 1 {
+                "conflict"
+            } else {
+                "conflicts"
+            }
+        );
+    }
+
+    let mut details = vec![];
+    for conflict in conflicts {
+        if let Some(r) = resolve_conflict(env, index, &conflict, auto_resolved)? {
+            details.push(r);
+        }
+    }
+
+    Ok(details)
+}
+
+fn conflict_markers(
+    style: ConflictStyle,
+    branch_name: &str,
+    upstream_name: &str,
+    upstream: Oid,
+    picked: &git2::Commit,
+) -> ConflictMarkers {
+    ConflictMarkers {
+        style,
+        main_label: Some(format!("{upstream_name} ({})", short_id(upstream))),
+        base_label: Some("base".to_string()),
+        branch_label: Some(format!(
+            "{branch_name} ({} {})",
+            short_id(picked.id()),
+            picked.summary().unwrap_or_default()
+        )),
+    }
+}
+
+fn merge_options() -> MergeOptions {
+    let mut options = MergeOptions::new();
+    options
+        .find_renames(true)
+        .rename_threshold(RENAME_THRESHOLD);
+    options
+}
+
+fn rebase_options<'a>() -> RebaseOptions<'a> {
+    let mut options = RebaseOptions::new();
+    options.inmemory(true).merge_options(merge_options());
+    options
+}
+
+fn find_merge_commits(repo: &Repository, main: &Commit, branch: &Commit) -> Maybe<Vec<Oid>> {
+    let mut walk = repo.revwalk()?;
+    walk.push(branch.id())?;
+    walk.hide(main.id())?;
+
+    let mut merges = vec![];
+    for id in walk {
+        let id = id?;
+        if repo.find_commit(id)?.parent_count() > 1 {
+            merges.push(id);
+        }
+    }
+    Ok(merges)
+}
+
+fn merge_strategy(
     ctx: &Ctx,
     branch_name: &str,
-    resolutions: Option<&ResolutionMap>,
-) -> Maybe<SyncDetails> {
+    upstream_name: &str,
+    merges: &[Oid],
+    options: &SyncOptions,
+) -> Maybe<MergeCommits> {
+    if let Some(strategy) = options.merges {
+        return Ok(strategy);
+    }
+    if let Some(strategy) = get_config_enum(ctx, "itch.sync.merges")? {
+        return Ok(strategy);
+    }
+
+    let count = if merges.len() == 1 {
+        "a merge commit".to_string()
+    } else {
+        format!("{} merge commits", merges.len())
+    };
+
+    if !ctx.can_prompt() || options.no_prompt {
+        let ids: Vec<String> = merges.iter().map(|id| short_id(*id)).collect();
+        return fail!(format!(
+            "{branch_name} contains {count} ({}). Sync with --merges linearize to replay its saves onto {upstream_name}, or --merges preserve to merge {upstream_name} into it.",
+            ids.join(", ")
+        ));
+    }
+
+    match ask_option(
+        &format!(
+            "{branch_name} contains {count}. Linearize to replay its saves onto {upstream_name}, or preserve to merge {upstream_name} into it?"
+        ),
+        &["linearize", "preserve", "cancel"],
+        Some("linearize"),
+    )
+    .as_str()
+    {
+        "linearize" => Ok(MergeCommits::Linearize),
+        "preserve" => Ok(MergeCommits::Preserve),
+        _ => fail!("Sync cancelled."),
+    }
+}
+
+fn sync_mode(ctx: &Ctx, branch_name: &str, mode: Option<SyncMode>) -> Maybe<SyncMode> {
+    if let Some(mode) = mode {
+        return Ok(mode);
+    }
+    if let Some(mode) = get_config_enum(ctx, &format!("branch.{branch_name}.itchSyncMode"))? {
+        return Ok(mode);
+    }
+    Ok(get_config_enum(ctx, "itch.sync.mode")?.unwrap_or(SyncMode::Rebase))
+}
+
+fn squash_commit<'a>(ctx: &'a Ctx, main: &Commit, branch: &Commit<'a>) -> Maybe<Commit<'a>> {
     let repo = &ctx.repo;
-    let branch_ref = repo
+    let tip = skip_temp_commits(branch.clone());
+    let fork_id = repo.merge_base(main.id(), tip.id())?;
+    if tip.id() == fork_id {
+        return Ok(branch.clone());
+    }
+    let message = resolve_squashed_message(ctx, &[], tip.clone(), fork_id)?;
+    let signature = repo.signature()?;
+    let id = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &message,
+        &tip.tree()?,
+        &[&repo.find_commit(fork_id)?],
+    )?;
+    restack_temp_commits(repo, branch, &tip, repo.find_commit(id)?)
+}
+
+fn restack_temp_commits<'a>(
+    repo: &'a Repository,
+    branch: &Commit<'a>,
+    tip: &Commit,
+    onto: Commit<'a>,
+) -> Maybe<Commit<'a>> {
+    let mut temps = vec![];
+    let mut commit = branch.clone();
+    while commit.id() != tip.id() {
+        let parent = commit.parent(0)?;
+        temps.push(commit);
+        commit = parent;
+    }
+
+    let mut parent = onto;
+    for temp in temps.iter().rev() {
+        let id = repo.commit(
+            None,
+            &temp.author(),
+            &temp.committer(),
+            temp.message().unwrap_or_default(),
+            &temp.tree()?,
+            &[&parent],
+        )?;
+        parent = repo.find_commit(id)?;
+    }
+    Ok(parent)
+}
+
+pub fn update_branch(repo: &Repository, branch_name: &str, id: Oid) -> Attempt {
+    let commit = repo.find_commit(id)?;
+    if repo
         .find_branch(branch_name, git2::BranchType::Local)?
-        .into_reference();
-    let main_ref = repo
-        .find_branch("main", git2::BranchType::Local)?
-        .into_reference();
+        .is_head()
+    {
+        repo.reset(commit.as_object(), git2::ResetType::Hard, None)?;
+    } else {
+        repo.branch(branch_name, &commit, true)?;
+    }
+    Ok(())
+}
+
+fn merge_upstream_into(env: &SyncEnv, main: &Commit, branch: &Commit) -> Maybe<SyncReport> {
+    let repo = &env.ctx.repo;
+    let mut auto_resolved: Vec<String> = vec![];
+    let tip = skip_temp_commits(branch.clone());
 
-    let upstream_id = repo.reference_to_annotated_commit(&main_ref)?;
-    let branch_id = repo.reference_to_annotated_commit(&branch_ref)?;
+    let mut index = repo.merge_commits(main, &tip, Some(&merge_options()))?;
+
+    if index.has_conflicts() {
+        let details = resolve_index(env, &mut index, &mut auto_resolved)?;
+        if !details.is_empty() {
+            return Ok(SyncReport {
+                details: SyncDetails::Conflicted(details),
+                auto_resolved,
+            });
+        }
+    }
+
+    let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+    let signature = repo.signature()?;
+    let id = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &format!("Merge {} into {}", env.upstream_name, env.branch_name),
+        &tree,
+        &[&tip, main],
+    )?;
+
+    if tip.id() == branch.id() {
+        update_branch(repo, env.branch_name, id)?;
+        return Ok(SyncReport {
+            details: SyncDetails::Complete,
+            auto_resolved,
+        });
+    }
+
+    let (mut report, id) = replay_range(env, &tip, &repo.find_commit(id)?, branch)?;
+    auto_resolved.append(&mut report.auto_resolved);
+    report.auto_resolved = auto_resolved;
+    if matches!(report.details, SyncDetails::Complete) {
+        update_branch(repo, env.branch_name, id)?;
+    }
+    Ok(report)
+}
+
+fn rebase_onto(env: &SyncEnv, main: &Commit, branch: &Commit) -> Maybe<SyncReport> {
+    let (report, id) = replay_range(env, main, main, branch)?;
+    if matches!(report.details, SyncDetails::Complete) {
+        update_branch(&env.ctx.repo, env.branch_name, id)?;
+    }
+    Ok(report)
+}
+
+fn replay_range(
+    env: &SyncEnv,
+    upstream: &Commit,
+    onto: &Commit,
+    branch: &Commit,
+) -> Maybe<(SyncReport, Oid)> {
+    let repo = &env.ctx.repo;
+    let branch_name = env.branch_name;
+    let mut auto_resolved: Vec<String> = vec![];
+
+    let upstream_id = repo.find_annotated_commit(upstream.id())?;
+    let onto_id = repo.find_annotated_commit(onto.id())?;
+    let branch_id = repo.find_annotated_commit(branch.id())?;
 
     let mut rebase = repo.rebase(
         Some(&branch_id),
         Some(&upstream_id),
-        None,
-        Some(&mut RebaseOptions::new().inmemory(true)),
+        Some(&onto_id),
+        Some(&mut rebase_options()),
     )?;
 
-    let mut final_id: Oid = upstream_id.id();
+    let mut final_id: Oid = onto.id();
 
     let mut details: Vec<Conflict> = vec![];
 
-    while let Some(Ok(operation)) = rebase.next() {
+    while let Some(operation) = rebase.next() {
+        let operation = operation?;
         match operation.kind() {
             Some(RebaseOperationType::Pick) => {
                 let mut index = rebase.inmemory_index()?;
                 if index.has_conflicts() {
-                    let mut conflicts: Vec<IndexConflict> = vec![];
-
-                    rebase
-                        .inmemory_index()?
-                        .conflicts()?
-                        .try_for_each(|c| -> Attempt {
-                            conflicts.push(c?);
-                            Ok(())
-                        })?;
-
-                    if ctx.can_prompt() {
-                        eprintln!(
-                            "\nThere are {} {} to resolve.",
-                            conflicts.len(),
-                            if conflicts.len() == 1 {
-                                "conflict"
-                            } else {
-                                "conflicts"
-                            }
-                        );
-                    }
+                    let picked = repo.find_commit(operation.id())?;
+                    let env = SyncEnv {
+                        markers: conflict_markers(
+                            env.markers.style,
+                            env.incoming_name,
+                            env.upstream_name,
+                            onto.id(),
+                            &picked,
+                        ),
+                        ..*env
+                    };
 
-                    conflicts.into_iter().try_for_each(|conflict| -> Attempt {
-                        if let Some(r) = resolve_conflict(ctx, &mut index, &conflict, resolutions)?
-                        {
-                            details.push(r);
-                        }
-                        Ok(())
-                    })?;
+                    details.extend(resolve_index(&env, &mut index, &mut auto_resolved)?);
                 }
             }
-            Some(RebaseOperationType::Fixup) => {
-                // Ok, whatever
-                todo!("Handle fixup");
-            }
-            Some(RebaseOperationType::Edit) => {
-                todo!("Handle edit");
-            }
-            _ => {
-                todo!("Handle: {:?}", operation);
+            kind => {
+                return fail!(format!(
+                    "Cannot replay {} while syncing {branch_name}: unexpected {kind:?} step.",
+                    short_id(operation.id())
+                ));
             }
         }
 
         if !details.is_empty() {
-            return Ok(SyncDetails::Conflicted(details));
+            return Ok((
+                SyncReport {
+                    details: SyncDetails::Conflicted(details),
+                    auto_resolved,
+                },
+                final_id,
+            ));
         }
 
         match rebase.commit(None, &repo.signature()?, None) {
@@ -308,37 +914,602 @@ pub fn try_sync_branch(
 
     rebase.finish(Some(&repo.signature()?))?;
 
-    let final_commit = repo.find_commit(final_id)?;
-    if repo
+    Ok((
+        SyncReport {
+            details: SyncDetails::Complete,
+            auto_resolved,
+        },
+        final_id,
+    ))
+}
+
+pub fn replay_saves(
+    ctx: &Ctx,
+    branch_name: &str,
+    incoming_name: &str,
+    (after, tip): (&Commit, &Commit),
+    onto: &Commit,
+    options: &SyncOptions,
+) -> Maybe<(SyncReport, Oid)> {
+    let rules = load_rules(ctx)?;
+    let style: ConflictStyle = get_config_enum(ctx, "merge.conflictStyle")?.unwrap_or_default();
+    let env = SyncEnv {
+        ctx,
+        branch_name,
+        incoming_name,
+        upstream_name: branch_name,
+        options,
+        rules: &rules,
+        markers: conflict_markers(style, incoming_name, branch_name, onto.id(), tip),
+        builtin_only: false,
+    };
+    replay_range(&env, after, onto, tip)
+}
+
+fn preview_index(
+    env: &SyncEnv,
+    index: &mut Index,
+    picked: &Commit,
+) -> Maybe<Option<ConflictPreview>> {
+    if !index.has_conflicts() {
+        return Ok(None);
+    }
+
+    let mut conflicts: Vec<IndexConflict> = vec![];
+    index.conflicts()?.try_for_each(|c| -> Attempt {
+        conflicts.push(c?);
+        Ok(())
+    })?;
+
+    let mut paths = vec![];
+    for conflict in pair_renames(&env.ctx.repo, index, conflicts)? {
+        if resolve_automatically(env, index, &conflict)?.is_none() {
+            paths.push(conflict_path(env, &conflict)?.to_string());
+            apply_resolution(env, index, &conflict, &ResolutionChoice::Incoming)?;
+        }
+    }
+
+    Ok((!paths.is_empty()).then(|| ConflictPreview {
+        save: format!(
+            "{} {}",
+            short_id(picked.id()),
+            picked.summary().unwrap_or_default()
+        ),
+        paths,
+    }))
+}
+
+pub fn preview_sync(
+    ctx: &Ctx,
+    branch_name: &str,
+    upstream: Option<&str>,
+    merges: Option<MergeCommits>,
+    mode: Option<SyncMode>,
+) -> Maybe<Vec<ConflictPreview>> {
+    let repo = &ctx.repo;
+    let rules = load_rules(ctx)?;
+    let options = SyncOptions {
+        no_prompt: true,
+        upstream,
+        ..Default::default()
+    };
+
+    let branch = repo
         .find_branch(branch_name, git2::BranchType::Local)?
-        .is_head()
+        .into_reference()
+        .peel_to_commit()?;
+    let upstream_name = upstream.unwrap_or("main");
+    let main = repo.revparse_single(upstream_name)?.peel_to_commit()?;
+
+    if repo.merge_base(main.id(), branch.id())? == main.id() {
+        return Ok(vec![]);
+    }
+
+    let env = SyncEnv {
+        ctx,
+        branch_name,
+        incoming_name: branch_name,
+        upstream_name,
+        options: &options,
+        rules: &rules,
+        markers: ConflictMarkers::default(),
+        builtin_only: true,
+    };
+
+    let mode = sync_mode(ctx, branch_name, mode)?;
+    if mode == SyncMode::Squash {
+        let fork = repo.find_commit(repo.merge_base(main.id(), branch.id())?)?;
+        let mut index = repo.merge_trees(
+            &fork.tree()?,
+            &main.tree()?,
+            &branch.tree()?,
+            Some(&merge_options()),
+        )?;
+        return Ok(preview_index(&env, &mut index, &branch)?
+            .into_iter()
+            .collect());
+    }
+
+    let strategy = match merges {
+        Some(strategy) => Some(strategy),
+        None => get_config_enum(ctx, "itch.sync.merges")?,
+    };
+    if mode == SyncMode::Merge
+        || (strategy == Some(MergeCommits::Preserve)
+            && !find_merge_commits(repo, &main, &branch)?.is_empty())
     {
-        repo.reset(final_commit.as_object(), git2::ResetType::Hard, None)?;
-    } else {
-        repo.branch(branch_name, &final_commit, true)?;
+        let mut index = repo.merge_commits(&main, &branch, Some(&merge_options()))?;
+        return Ok(preview_index(&env, &mut index, &branch)?
+            .into_iter()
+            .collect());
+    }
+
+    let mut rebase = repo.rebase(
+        Some(&repo.find_annotated_commit(branch.id())?),
+        Some(&repo.find_annotated_commit(main.id())?),
+        None,
+        Some(&mut rebase_options()),
+    )?;
+
+    let mut previews = vec![];
+    while let Some(operation) = rebase.next() {
+        let operation = operation?;
+        let picked = repo.find_commit(operation.id())?;
+        let mut index = rebase.inmemory_index()?;
+        previews.extend(preview_index(&env, &mut index, &picked)?);
+
+        if let Err(e) = rebase.commit(None, &repo.signature()?, None)
+            && e.code() != ErrorCode::Applied
+        {
+            return Err(e.into());
+        }
+    }
+
+    rebase.abort()?;
+
+    Ok(previews)
+}
+
+pub fn sync_conflict_count(ctx: &Ctx, branch_name: &str) -> usize {
+    match preview_sync(ctx, branch_name, None, None, None) {
+        Ok(previews) => count_conflicting_files(&previews),
+        Err(e) => {
+            show_warning(
+                ctx,
+                &format!("Could not check {branch_name} for sync conflicts ({e})"),
+            );
+            0
+        }
+    }
+}
+
+pub fn try_sync_branch(ctx: &Ctx, branch_name: &str, options: &SyncOptions) -> Maybe<SyncReport> {
+    let repo = &ctx.repo;
+    let rules = load_rules(ctx)?;
+    let style: ConflictStyle = get_config_enum(ctx, "merge.conflictStyle")?.unwrap_or_default();
+
+    let branch = repo
+        .find_branch(branch_name, git2::BranchType::Local)?
+        .into_reference()
+        .peel_to_commit()?;
+    let upstream_name = options.upstream.unwrap_or("main");
+    if upstream_name == branch_name {
+        return fail!(format!("Cannot sync {branch_name} from itself."));
+    }
+    let main = repo.revparse_single(upstream_name)?.peel_to_commit()?;
+
+    let env = SyncEnv {
+        ctx,
+        branch_name,
+        incoming_name: branch_name,
+        upstream_name,
+        options,
+        rules: &rules,
+        markers: conflict_markers(style, branch_name, upstream_name, main.id(), &branch),
+        builtin_only: false,
+    };
+
+    if repo.merge_base(main.id(), branch.id())? == main.id() {
+        return Ok(SyncReport {
+            details: SyncDetails::Complete,
+            auto_resolved: vec![],
+        });
+    }
+
+    match sync_mode(ctx, branch_name, options.mode)? {
+        SyncMode::Merge => return merge_upstream_into(&env, &main, &branch),
+        SyncMode::Squash => return rebase_onto(&env, &main, &squash_commit(ctx, &main, &branch)?),
+        SyncMode::Rebase => {}
+    }
+
+    let merges = find_merge_commits(repo, &main, &branch)?;
+    if !merges.is_empty()
+        && merge_strategy(ctx, branch_name, upstream_name, &merges, options)?
+            == MergeCommits::Preserve
+    {
+        return merge_upstream_into(&env, &main, &branch);
     }
 
-    Ok(SyncDetails::Complete)
+    rebase_onto(&env, &main, &branch)
+}
+
+fn print_auto_resolved(ctx: &Ctx, report: &SyncReport) {
+    if ctx.can_prompt() && !report.auto_resolved.is_empty() {
+        eprintln!("Resolved automatically:");
+        for line in &report.auto_resolved {
+            eprintln!("  {line}");
+        }
+    }
 }
 
-fn sync_branch(ctx: &Ctx, branch_name: &str) -> Attempt {
-    match try_sync_branch(ctx, branch_name, None)? {
+fn sync_branch(ctx: &Ctx, branch_name: &str, options: &SyncOptions) -> Attempt {
+    let report = try_sync_branch(ctx, branch_name, options)?;
+
+    print_auto_resolved(ctx, &report);
+
+    match report.details {
         SyncDetails::Complete => Ok(()),
-        SyncDetails::Conflicted(_) => fail!("Still conflicted after sync."),
+        SyncDetails::Conflicted(conflicts) => {
+            let paths: Vec<&str> = conflicts.iter().map(Conflict::path).collect();
+            fail!(format!(
+                "Still conflicted after sync: {}. Pass --resolve or --resolutions to choose.",
+                paths.join(", ")
+            ))
+        }
     }
 }
 
+fn all_branch_names(ctx: &Ctx, upstream: Option<&str>) -> Maybe<Vec<String>> {
+    let mut names = vec![];
+    for branch in ctx.repo.branches(Some(git2::BranchType::Local))? {
+        if let Some(name) = branch?.0.name()?
+            && name != "main"
+            && Some(name) != upstream
+        {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+fn sync_all(ctx: &Ctx, options: &SyncOptions) -> Maybe<bool> {
+    let names = all_branch_names(ctx, options.upstream)?;
+
+    let mut summary = vec![];
+    let mut all_synced = true;
+
+    for name in &names {
+        let before = ctx.repo.revparse_single(name)?.id();
+        let outcome = match try_sync_branch(ctx, name, options) {
+            Ok(report) => {
+                print_auto_resolved(ctx, &report);
+                match report.details {
+                    SyncDetails::Complete if ctx.repo.revparse_single(name)?.id() == before => {
+                        "up to date".to_string()
+                    }
+                    SyncDetails::Complete if report.auto_resolved.is_empty() => {
+                        "synced".to_string()
+                    }
+                    SyncDetails::Complete => format!(
+                        "synced, {} resolved automatically",
+                        report.auto_resolved.len()
+                    ),
+                    SyncDetails::Conflicted(conflicts) => {
+                        all_synced = false;
+                        let paths: Vec<&str> = conflicts.iter().map(Conflict::path).collect();
+                        format!("conflicted in {}", paths.join(", "))
+                    }
+                }
+            }
+            Err(e) => {
+                all_synced = false;
+                format!("failed: {e}")
+            }
+        };
+        summary.push((name, outcome));
+    }
+
+    for (name, outcome) in summary {
+        println!("{name}: {outcome}");
+    }
+
+    Ok(all_synced)
+}
+
+fn print_preview(ctx: &Ctx, branch_name: &str, upstream: Option<&str>, args: &SyncArgs) -> Attempt {
+    let previews = preview_sync(ctx, branch_name, upstream, args.merges, args.mode)?;
+    match conflict_badge(count_conflicting_files(&previews)) {
+        Some(badge) => println!("{branch_name}: {badge}"),
+        None => println!("{branch_name}: no conflicts"),
+    }
+    for preview in previews {
+        println!("  {}: {}", preview.save, preview.paths.join(", "));
+    }
+    Ok(())
+}
+
+fn parse_sync_names(names: &[String]) -> Maybe<(Option<&str>, &[String])> {
+    match names {
+        [first, upstream, rest @ ..] if first == "from" => Ok((Some(upstream), rest)),
+        [first] if first == "from" => fail!("Missing branch, tag or commit to sync from."),
+        _ => Ok((None, names)),
+    }
+}
+
+fn load_resolutions(args: &SyncArgs) -> Maybe<(ResolutionMap, Option<ResolutionChoice>)> {
+    let mut resolutions = match &args.resolutions {
+        Some(file) => {
+            let text = std::fs::read_to_string(file)
+                .map_err(|e| inner_fail!(format!("Could not read {file}: {e}")))?;
+            serde_json::from_str::<ResolutionMap>(&text)
+                .map_err(|e| inner_fail!(format!("Could not parse {file}: {e}")))?
+        }
+        None => ResolutionMap::new(),
+    };
+
+    let mut default_resolution = None;
+    for value in &args.resolve {
+        let (path, choice) = match value.rsplit_once('=') {
+            Some((path, choice)) => (Some(path), choice),
+            None => (None, value.as_str()),
+        };
+        let Some(resolution) = ResolutionChoice::parse(choice) else {
+            return fail!(format!(
+                "Unknown resolution {choice}. Use keep, reset or later."
+            ));
+        };
+        match path {
+            Some(path) => {
+                resolutions.insert(path.to_string(), resolution);
+            }
+            None if default_resolution.is_some() => {
+                return fail!("Only one default resolution can be passed.");
+            }
+            None => default_resolution = Some(resolution),
+        }
+    }
+
+    Ok((resolutions, default_resolution))
+}
+
 pub fn sync_command(ctx: &Ctx, args: &SyncArgs) -> Attempt {
+    let (upstream, names) = parse_sync_names(&args.names)?;
+    if args.all && !names.is_empty() {
+        return fail!("Pass either branch names or --all, not both.");
+    }
+
+    if args.dry_run {
+        let names = if args.all {
+            all_branch_names(ctx, upstream)?
+        } else if names.is_empty() {
+            vec![get_current_branch(ctx)?]
+        } else {
+            names.to_vec()
+        };
+        for name in &names {
+            print_preview(ctx, name, upstream, args)?;
+        }
+        return Ok(());
+    }
+
+    let (resolutions, default_resolution) = load_resolutions(args)?;
+
+    let options = SyncOptions {
+        resolutions: Some(&resolutions),
+        default_resolution,
+        merges: args.merges,
+        mode: args.mode,
+        upstream,
+        ..Default::default()
+    };
+
     save_temp(ctx, "Save before sync".to_string())?;
 
     try_pull_main(ctx);
 
-    if args.names.is_empty() {
-        sync_branch(ctx, &get_current_branch(ctx)?)?;
+    let mut all_synced = true;
+    if args.all {
+        all_synced = sync_all(ctx, &options)?;
+    } else if names.is_empty() {
+        sync_branch(ctx, &get_current_branch(ctx)?, &options)?;
+    }
+    for branch in names {
+        sync_branch(ctx, branch, &options)?;
     }
-    for branch in &args.names {
-        sync_branch(ctx, branch)?;
+
+    pop_and_reset(ctx)?;
+
+    if ctx.can_prompt() && !unresolved_in_workdir(ctx, &get_current_branch(ctx)?)?.is_empty() {
+        eprintln!("Some conflicts were left for later. Run `itch resolve` to finish them.");
     }
 
-    pop_and_reset(ctx)
+    if !all_synced {
+        return fail!("Some branches could not be synced.");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use git2::{Commit, Index, IndexConflict, IndexEntry, IndexTime, Oid, Repository};
+    use tempfile::TempDir;
+
+    use super::{extract_path, merge_renamed, pair_renames, try_sync_branch};
+    use crate::{
+        cli::{MergeCommits, SyncMode},
+        consts::TEMP_COMMIT_PREFIX,
+        ctx::{Ctx, test_ctx},
+        diff::merge_tests::init_repo,
+        reset::skip_temp_commits,
+        sync::{SyncDetails, SyncOptions},
+    };
+
+    fn entry(path: &str, id: Oid, stage: u16) -> IndexEntry {
+        IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100_644,
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id,
+            flags: stage << 12,
+            flags_extended: 0,
+            path: path.as_bytes().to_vec(),
+        }
+    }
+
+    fn paired_conflict(repo: &Repository, main: &str, branch: &str) -> IndexConflict {
+        let base = repo.blob(b"one\ntwo\nthree\nfour\nfive\nsix\n").unwrap();
+        let main = repo.blob(main.as_bytes()).unwrap();
+        let branch = repo.blob(branch.as_bytes()).unwrap();
+
+        let mut index = Index::new().unwrap();
+        index.add(&entry("old.txt", base, 1)).unwrap();
+        index.add(&entry("old.txt", main, 2)).unwrap();
+        index.add(&entry("new.txt", branch, 3)).unwrap();
+
+        let conflicts: Vec<IndexConflict> =
+            index.conflicts().unwrap().map(Result::unwrap).collect();
+        let mut paired = pair_renames(repo, &mut index, conflicts).unwrap();
+        assert_eq!(paired.len(), 1);
+        paired.remove(0)
+    }
+
+    #[test]
+    fn merges_paired_rename() {
+        let (_dir, repo) = init_repo();
+        let conflict = paired_conflict(
+            &repo,
+            "ONE\ntwo\nthree\nfour\nfive\nsix\n",
+            "one\ntwo\nthree\nfour\nfive\nSIX\n",
+        );
+        assert_eq!(extract_path(&conflict).unwrap().to_str(), Some("new.txt"));
+        assert_eq!(
+            merge_renamed(&repo, &conflict).unwrap().as_deref(),
+            Some("ONE\ntwo\nthree\nfour\nfive\nSIX\n")
+        );
+    }
+
+    #[test]
+    fn keeps_conflicting_rename() {
+        let (_dir, repo) = init_repo();
+        let conflict = paired_conflict(
+            &repo,
+            "ONE\ntwo\nthree\nfour\nfive\nsix\n",
+            "uno\ntwo\nthree\nfour\nfive\nsix\n",
+        );
+        assert!(merge_renamed(&repo, &conflict).unwrap().is_none());
+    }
+
+    fn commit(repo: &Repository, parents: &[Oid], message: &str, files: &[(&str, &str)]) -> Oid {
+        let parents: Vec<Commit> = parents
+            .iter()
+            .map(|id| repo.find_commit(*id).unwrap())
+            .collect();
+        let base = parents.first().map(|p| p.tree().unwrap());
+        let mut builder = repo.treebuilder(base.as_ref()).unwrap();
+        for (name, text) in files {
+            let blob = repo.blob(text.as_bytes()).unwrap();
+            builder.insert(name, blob, 0o100_644).unwrap();
+        }
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = repo.signature().unwrap();
+        let parents: Vec<&Commit> = parents.iter().collect();
+        repo.commit(None, &signature, &signature, message, &tree, &parents)
+            .unwrap()
+    }
+
+    fn sync_dirty(options: &SyncOptions, merge_side: bool) -> (TempDir, Ctx) {
+        let (dir, repo) = init_repo();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        let base = commit(&repo, &[], "Init", &[("a.txt", "a\n")]);
+        let main = commit(&repo, &[base], "Main change", &[("m.txt", "m\n")]);
+        let mut tip = commit(&repo, &[base], "Feature", &[("f.txt", "f\n")]);
+        if merge_side {
+            let side = commit(&repo, &[base], "Side", &[("s.txt", "s\n")]);
+            tip = commit(&repo, &[tip, side], "Merge side", &[("s.txt", "s\n")]);
+        }
+        let temp = commit(
+            &repo,
+            &[tip],
+            &format!("{TEMP_COMMIT_PREFIX} Save before sync"),
+            &[("dirty.txt", "dirty\n")],
+        );
+        repo.reference("refs/heads/main", main, true, "").unwrap();
+        repo.reference("refs/heads/feat", temp, true, "").unwrap();
+
+        let ctx = test_ctx(repo);
+        let report = try_sync_branch(&ctx, "feat", options).unwrap();
+        assert!(matches!(report.details, SyncDetails::Complete));
+        (dir, ctx)
+    }
+
+    fn synced_below_temp(ctx: &Ctx) -> Commit<'_> {
+        let feat = ctx
+            .repo
+            .revparse_single("feat")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert!(feat.message().unwrap().starts_with(TEMP_COMMIT_PREFIX));
+        assert!(feat.tree().unwrap().get_name("dirty.txt").is_some());
+
+        let synced = skip_temp_commits(feat);
+        let tree = synced.tree().unwrap();
+        assert!(tree.get_name("dirty.txt").is_none());
+        assert!(tree.get_name("m.txt").is_some());
+        assert!(tree.get_name("f.txt").is_some());
+        synced
+    }
+
+    #[test]
+    fn squash_sync_keeps_temp_save_on_top() {
+        let (_dir, ctx) = sync_dirty(
+            &SyncOptions {
+                mode: Some(SyncMode::Squash),
+                ..Default::default()
+            },
+            false,
+        );
+        let synced = synced_below_temp(&ctx);
+        let main = ctx.repo.revparse_single("main").unwrap().id();
+        assert_eq!(synced.summary(), Some("Feature"));
+        assert_eq!(synced.parent_ids().collect::<Vec<_>>(), vec![main]);
+    }
+
+    #[test]
+    fn merge_sync_keeps_temp_save_on_top() {
+        let (_dir, ctx) = sync_dirty(
+            &SyncOptions {
+                mode: Some(SyncMode::Merge),
+                ..Default::default()
+            },
+            false,
+        );
+        let synced = synced_below_temp(&ctx);
+        assert_eq!(synced.summary(), Some("Merge main into feat"));
+        assert_eq!(synced.parent(0).unwrap().summary(), Some("Feature"));
+    }
+
+    #[test]
+    fn preserved_merge_sync_keeps_temp_save_on_top() {
+        let (_dir, ctx) = sync_dirty(
+            &SyncOptions {
+                merges: Some(MergeCommits::Preserve),
+                ..Default::default()
+            },
+            true,
+        );
+        let synced = synced_below_temp(&ctx);
+        assert_eq!(synced.summary(), Some("Merge main into feat"));
+        assert_eq!(synced.parent(0).unwrap().summary(), Some("Merge side"));
+        assert!(synced.tree().unwrap().get_name("s.txt").is_some());
+    }
 }
@@ -0,0 +1,121 @@
+use git2::Oid;
+
+use crate::{
+    branch::get_current_branch,
+    cli::TransferArgs,
+    commit::{find_save, list_saves, short_summary},
+    ctx::Ctx,
+    error::{Attempt, Maybe, fail},
+    reset::pop_and_reset,
+    save::save_temp,
+    sync::{Conflict, SyncDetails, SyncOptions, SyncReport},
+};
+
+use super::sync::{replay_saves, update_branch};
+
+fn parse_transfer_args(args: &[String]) -> Maybe<(String, &str)> {
+    match args {
+        [query @ .., to, target] if to == "to" && !query.is_empty() => {
+            Ok((query.join(" "), target))
+        }
+        _ => fail!("Expected a save and a branch, for example: itch move 1 to other-branch"),
+    }
+}
+
+fn expect_complete((report, id): (SyncReport, Oid), action: &str) -> Maybe<Oid> {
+    match report.details {
+        SyncDetails::Complete => Ok(id),
+        SyncDetails::Conflicted(conflicts) => {
+            let paths: Vec<&str> = conflicts.iter().map(Conflict::path).collect();
+            fail!(format!(
+                "Could not {action}, still conflicted: {}.",
+                paths.join(", ")
+            ))
+        }
+    }
+}
+
+fn transfer(ctx: &Ctx, args: &TransferArgs, remove: bool) -> Attempt {
+    let (query, target_name) = parse_transfer_args(&args.args)?;
+    let branch_name = get_current_branch(ctx)?;
+    if target_name == branch_name {
+        return fail!(format!("The save is already on {branch_name}."));
+    }
+    let target = ctx
+        .repo
+        .find_branch(target_name, git2::BranchType::Local)?
+        .into_reference()
+        .peel_to_commit()?;
+
+    let saves = list_saves(ctx, &ctx.repo.head()?.peel_to_commit()?)?;
+    let save = find_save(&saves, &query)?;
+    if save.parent_count() > 1 {
+        return fail!(format!(
+            "{} is a merge commit and cannot be moved.",
+            short_summary(save)
+        ));
+    }
+    let parent = save.parent(0)?;
+
+    let action = if remove { "move" } else { "copy" };
+    save_temp(ctx, format!("Save before {action}"))?;
+    let head = ctx.repo.head()?.peel_to_commit()?;
+
+    let options = SyncOptions::default();
+    let result = (|| -> Attempt {
+        let target_id = expect_complete(
+            replay_saves(
+                ctx,
+                target_name,
+                &branch_name,
+                (&parent, save),
+                &target,
+                &options,
+            )?,
+            &format!("apply the save to {target_name}"),
+        )?;
+
+        let source_id = if remove {
+            Some(expect_complete(
+                replay_saves(
+                    ctx,
+                    &branch_name,
+                    &branch_name,
+                    (save, &head),
+                    &parent,
+                    &options,
+                )?,
+                &format!("remove the save from {branch_name}"),
+            )?)
+        } else {
+            None
+        };
+
+        update_branch(&ctx.repo, target_name, target_id)?;
+        if let Some(source_id) = source_id {
+            update_branch(&ctx.repo, &branch_name, source_id)?;
+        }
+        Ok(())
+    })();
+
+    pop_and_reset(ctx)?;
+    result?;
+
+    if ctx.can_prompt() {
+        eprintln!(
+            "{} {} to {target_name}",
+            if remove { "Moved" } else { "Copied" },
+            short_summary(save)
+        );
+    }
+
+    Ok(())
+}
+
+pub fn move_command(ctx: &Ctx, args: &TransferArgs) -> Attempt {
+    transfer(ctx, args, true)
+}
+
+pub fn copy_command(ctx: &Ctx, args: &TransferArgs) -> Attempt {
+    transfer(ctx, args, false)
+}
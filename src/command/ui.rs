@@ -18,15 +18,16 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     branch::get_current_branch,
-    cli::{DeleteArgs, LoadArgs, NewArgs, SaveArgs, SquashArgs},
+    cli::{DeleteArgs, LoadArgs, MergeArgs, NewArgs, PruneArgs, SaveArgs, SquashArgs},
     command::new::new_command,
     commit::count_commits_since,
     ctx::{Ctx, init_ctx},
     diff::{collapse_renames, good_diff_options, split_diff_line},
     error::{Attempt, Fail, Maybe, fail, inner_fail},
+    history::{HistoryStep, PlanAction, StepAction, check_plan, with_summary},
     reset::pop_and_reset,
     save::save_temp,
-    sync::{Conflict, ResolutionChoice, ResolutionMap, SyncDetails},
+    sync::{Conflict, ResolutionChoice, ResolutionMap, SyncDetails, SyncOptions, conflict_badge},
 };
 
 use axum::{
@@ -40,14 +41,16 @@ use axum::{
 use maud::{DOCTYPE, Markup, PreEscaped, html};
 
 use super::{
+    conflicts::conflict_matrix,
     delete::delete_command,
+    history::{branch_saves, list_hunks, rewrite_history},
     load::load_command,
-    merge::merge_command,
+    merge::{Landing, MergeQueueReport, cleanup_merged, merge_command, run_merge_queue},
     prune::prune_command,
     save::save_command,
     squash::squash_command,
     status::{FileStatus, ForkInfo, SegmentedStatus, resolve_fork_info},
-    sync::try_sync_branch,
+    sync::{sync_conflict_count, try_sync_branch},
 };
 
 #[derive(Clone)]
@@ -157,6 +160,9 @@ fn branch_entry(info: &DashboardInfo, branch: &BranchInfo) -> Markup {
     html! {
         li.spaced-across {
             span.grow .selected[info.current_branch == name] { (name) }
+            @if let Some(badge) = conflict_badge(branch.conflicting_files) {
+                span { (badge) }
+            }
             @if branch.commits_behind > 0 {
                 (action_btn("POST", "/api/sync", "Sync", &Some(named(name)), false))
             }
@@ -169,6 +175,7 @@ fn branch_entry(info: &DashboardInfo, branch: &BranchInfo) -> Markup {
 struct BranchInfo {
     name: String,
     commits_behind: usize,
+    conflicting_files: usize,
 }
 
 struct DashboardInfo {
@@ -264,10 +271,16 @@ fn load_dashboard_info() -> Maybe<DashboardInfo> {
             .find_commit(ctx.repo.merge_base(base_commit.id(), head_commit.id())?)?;
 
         let base_past_fork = count_commits_since(&ctx, &fork_point, &base_commit)?;
+        let conflicting_files = if base_past_fork > 0 && branch_name != "main" {
+            sync_conflict_count(&ctx, &branch_name)
+        } else {
+            0
+        };
 
         branches.push(BranchInfo {
             name: branch_name,
             commits_behind: base_past_fork,
+            conflicting_files,
         });
     }
 
@@ -333,6 +346,10 @@ fn render_dashboard(info: &DashboardInfo) -> Markup {
                             "to single commit"
                         }
 
+                        @if info.commits_ahead > 0 {
+                            a href="/history" { "Edit history" }
+                        }
+
                         div.spaced-across {
                             (action_btn("POST", "/api/sync", "Sync", &None, false))
                             @match info.commits_behind {
@@ -340,6 +357,9 @@ fn render_dashboard(info: &DashboardInfo) -> Markup {
                                 1 => ("1 commit behind"),
                                 n => {(n) " commits behind"},
                             }
+                            @if let Some(badge) = conflict_badge(info.fork_info.conflicting_files) {
+                                ", " (badge)
+                            }
                         }
 
                         form method="POST" action="/api/save" {
@@ -359,10 +379,20 @@ fn render_dashboard(info: &DashboardInfo) -> Markup {
                     div.spaced-down.big-col {
                         div.spaced-across {
                             h2 {"All Branches"}
-                            (action_btn("POST", "/api/prune", "Prune empty", &None, false))
+                            a href="/conflicts" { "Conflicts" }
+                            (action_btn("POST", "/api/prune", "Prune merged", &None, false))
                             (action_btn("POST", "/api/sync_all", "Sync all", &None, false))
                         }
 
+                        form method="POST" action="/api/merge_queue" .inline-form.spaced-across.end  {
+                            label.grow {
+                                "Merge queue"
+                                br;
+                                input .in name="names" placeholder="branch names, in order";
+                            }
+                            (btn("submit", "Merge all", false))
+                        }
+
                         form method="POST" action="/api/new" .inline-form.spaced-across.end  {
                             label.grow {
                                 "New branch"
@@ -394,7 +424,24 @@ async fn dashboard(jar: CookieJar, State(state): State<CsrfState>) -> impl IntoR
         .map_err(map_error_to_response)
 }
 
-fn render_sync(conflicts: &Vec<Conflict>, branch_name: Option<&str>) -> Markup {
+fn render_auto_resolved(auto_resolved: &[String]) -> Markup {
+    html! {
+        @if !auto_resolved.is_empty() {
+            p { "Resolved automatically:" }
+            ul {
+                @for line in auto_resolved {
+                    li { (line) }
+                }
+            }
+        }
+    }
+}
+
+fn render_sync(
+    conflicts: &Vec<Conflict>,
+    auto_resolved: &[String],
+    branch_name: Option<&str>,
+) -> Markup {
     html! {
         (DOCTYPE)
         head {
@@ -406,6 +453,8 @@ fn render_sync(conflicts: &Vec<Conflict>, branch_name: Option<&str>) -> Markup {
         body.spaced-down {
             h1 { "Sync" }
 
+            (render_auto_resolved(auto_resolved))
+
             form.spaced-down method="POST" action="/api/sync"  {
                 (branch_name.map(|b| hidden_args(&Some(named(b)))).unwrap_or_default().unwrap_or_default())
 
@@ -414,8 +463,8 @@ fn render_sync(conflicts: &Vec<Conflict>, branch_name: Option<&str>) -> Markup {
                       Conflict::MainDeletion(path) => {
                         fieldset {
                             (path) " has changes, but was deleted on main." br;
-                            (radio("Keep", path, "incoming", true))
-                            (radio("Delete", path, "base", false))
+                            (radio("Keep", &path.path, "incoming", true))
+                            (radio("Delete", &path.path, "base", false))
                         }
                       },
                       Conflict::BranchDeletion(path) => {
@@ -424,16 +473,16 @@ fn render_sync(conflicts: &Vec<Conflict>, branch_name: Option<&str>) -> Markup {
                              " was deleted on your branch, but was modified on main."
                              br;
 
-                            (radio("Keep", path, "base", true))
-                            (radio("Delete", path, "incoming", false))
+                            (radio("Keep", &path.path, "base", true))
+                            (radio("Delete", &path.path, "incoming", false))
                         }
                         },
                         Conflict::Merge(info) => {
                             fieldset {
                                 (info.path) " has conflicts." br;
-                                (radio("Keep your version", &info.path, "incoming", true))
-                                (radio("Reset to main version", &info.path, "base", false))
-                                (radio("Resolve conflicts later", &info.path, "later", false))
+                                (radio("Keep your version", &info.path.path, "incoming", true))
+                                (radio("Reset to main version", &info.path.path, "base", false))
+                                (radio("Resolve conflicts later", &info.path.path, "later", false))
 
                                 details {
                                     summary {"Your content"}
@@ -451,6 +500,14 @@ fn render_sync(conflicts: &Vec<Conflict>, branch_name: Option<&str>) -> Markup {
                                         }
                                     }
                                 }
+                                details {
+                                    summary {"Base content"}
+                                    pre {
+                                        code {
+                                            (info.base_content)
+                                        }
+                                    }
+                                }
                                 details {
                                     summary {"With conflicts"}
                                     pre {
@@ -466,11 +523,11 @@ fn render_sync(conflicts: &Vec<Conflict>, branch_name: Option<&str>) -> Markup {
                                 (path) "has conflicts." br;
                                 label {
                                     "Your version"
-                                    input type="radio" name=(path) value="incoming" checked;
+                                    input type="radio" name=(path.path) value="incoming" checked;
                                 }
                                 label {
                                     "Their version"
-                                    input type="radio" name=(path) value="base";
+                                    input type="radio" name=(path.path) value="base";
                                 }
                             }
                         }
@@ -486,8 +543,220 @@ fn render_sync(conflicts: &Vec<Conflict>, branch_name: Option<&str>) -> Markup {
     }
 }
 
+fn render_conflicts() -> Maybe<Markup> {
+    let mut ctx = init_ctx()?;
+    ctx.set_mode(crate::ctx::Mode::Background);
+
+    let matrix = conflict_matrix(&ctx, &[])?;
+
+    Ok(html! {
+        (DOCTYPE)
+        html {
+            head {
+                title {
+                    "Conflicts | " (get_workspace_name(&ctx)) " | itch ui"
+                }
+                (common_head_contents())
+            }
+            body.spaced-down {
+                h1 { "Conflicts between branches" }
+                a href="/" {"Back"}
+
+                @if matrix.names.len() < 2 {
+                    p { "Need at least two branches with changes to compare." }
+                } @else if matrix.overlaps.is_empty() {
+                    p { "No conflicts between " (matrix.names.len()) " branches." }
+                } @else {
+                    table.matrix {
+                        tr {
+                            th {}
+                            @for name in &matrix.names {
+                                th { (name) }
+                            }
+                        }
+                        @for first in &matrix.names {
+                            tr {
+                                th { (first) }
+                                @for second in &matrix.names {
+                                    @if first == second {
+                                        td { "-" }
+                                    } @else if let Some(paths) = matrix.paths(first, second) {
+                                        td.conflicted title=(paths.join("\n")) { (paths.len()) }
+                                    } @else {
+                                        td { "." }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    ul.spaced-down {
+                        @for overlap in &matrix.overlaps {
+                            li {
+                                b { (overlap.first) " and " (overlap.second) }
+                                ": " (overlap.paths.join(", "))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+const HISTORY_ACTIONS: [PlanAction; 5] = [
+    PlanAction::Pick,
+    PlanAction::Reword,
+    PlanAction::Fold,
+    PlanAction::Drop,
+    PlanAction::Split,
+];
+
+fn render_history() -> Maybe<Markup> {
+    let mut ctx = init_ctx()?;
+    ctx.set_mode(crate::ctx::Mode::Background);
+
+    let saves = branch_saves(&ctx)?;
+    let mut hunks = vec![];
+    for save in &saves {
+        hunks.push(list_hunks(&ctx, save)?);
+    }
+
+    Ok(html! {
+        (DOCTYPE)
+        html {
+            head {
+                title {
+                    "History | " (get_workspace_name(&ctx)) " | itch ui"
+                }
+                (common_head_contents())
+            }
+            body.spaced-down {
+                h1 { "Edit history" }
+                p {
+                    "Saves run from top to bottom, oldest first. Change the numbers to reorder them. "
+                    "Fold combines a save into the one above it. "
+                    "Split creates one save per hunk number, in number order."
+                }
+
+                form.spaced-down method="POST" action="/api/history" {
+                    @for (i, save) in saves.iter().enumerate() {
+                        @let id = save.id().to_string();
+                        @let save_hunks = &hunks[i];
+                        @let order = i + 1;
+                        fieldset.spaced-down {
+                            div.spaced-across.end {
+                                input .in type="number" min="1" name={"order-" (id)} value=(order);
+                                select .in name={"action-" (id)} {
+                                    @for action in HISTORY_ACTIONS {
+                                        option value=(action.name()) { (action.name()) }
+                                    }
+                                }
+                                input .in.grow name={"message-" (id)} value=(save.summary().unwrap_or_default());
+                                code { (&id[0..8]) }
+                            }
+                            @if save_hunks.len() > 1 {
+                                details {
+                                    summary { (save_hunks.len()) " hunks" }
+                                    @for (n, hunk) in save_hunks.iter().enumerate() {
+                                        div.spaced-across {
+                                            input .in type="number" min="1" name={"hunk-" (id) "-" (n)} value="1";
+                                            code { (hunk.path) " " (hunk.header) }
+                                        }
+                                        pre { code { (hunk.preview.join("\n")) } }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    (btn("submit", "Apply", saves.is_empty()))
+                }
+
+                a href="/" {"Cancel"}
+            }
+        }
+    })
+}
+
+async fn history() -> impl IntoResponse {
+    render_history().map_err(map_error_to_response)
+}
+
+type HistoryForm = HashMap<String, String>;
+
+fn convert_history_form(ctx: &Ctx, body: &HistoryForm) -> Maybe<Vec<HistoryStep>> {
+    let field = |name: &str, id: &str| -> Maybe<&String> {
+        body.get(&format!("{name}-{id}"))
+            .ok_or_else(|| inner_fail!(format!("Missing {name} for {id}")))
+    };
+
+    let mut ordered = vec![];
+    for (i, save) in branch_saves(ctx)?.iter().enumerate() {
+        let id = save.id().to_string();
+        let order: usize = field("order", &id)?
+            .parse()
+            .map_err(|_| inner_fail!("Expected a number for the order"))?;
+        let message = field("message", &id)?.trim().to_string();
+        let action = match PlanAction::parse(field("action", &id)?) {
+            Some(PlanAction::Pick) => StepAction::Pick,
+            Some(PlanAction::Reword) if !message.is_empty() => {
+                StepAction::Reword(with_summary(save.message().unwrap_or_default(), &message))
+            }
+            Some(PlanAction::Reword) => return fail!("Missing message to reword to."),
+            Some(PlanAction::Fold) => StepAction::Fold,
+            Some(PlanAction::Drop) => StepAction::Drop,
+            Some(PlanAction::Split) => {
+                let mut groups = vec![];
+                while let Some(group) = body.get(&format!("hunk-{id}-{}", groups.len())) {
+                    match group.parse::<usize>() {
+                        Ok(group) if group > 0 => groups.push(group),
+                        _ => return fail!("Expected a save number for each hunk"),
+                    }
+                }
+                if groups.is_empty() {
+                    return fail!("Only saves with several hunks can be split.");
+                }
+                StepAction::Split(groups)
+            }
+            None => return fail!("Unexpected selection"),
+        };
+        ordered.push((
+            order,
+            i,
+            HistoryStep {
+                id: save.id(),
+                action,
+            },
+        ));
+    }
+    ordered.sort_by_key(|(order, i, _)| (*order, *i));
+
+    let actions: Vec<PlanAction> = ordered
+        .iter()
+        .map(|(_, _, step)| match step.action {
+            StepAction::Pick => PlanAction::Pick,
+            StepAction::Reword(_) => PlanAction::Reword,
+            StepAction::Fold => PlanAction::Fold,
+            StepAction::Drop => PlanAction::Drop,
+            StepAction::Split(_) => PlanAction::Split,
+        })
+        .collect();
+    check_plan(&actions)?;
+
+    Ok(ordered.into_iter().map(|(_, _, step)| step).collect())
+}
+
+async fn handle_history(Form(body): Form<HistoryForm>) -> impl IntoResponse {
+    api_handler(move |ctx| rewrite_history(ctx, &convert_history_form(ctx, &body)?))
+}
+
+async fn conflicts() -> impl IntoResponse {
+    render_conflicts().map_err(map_error_to_response)
+}
+
 async fn sync() -> impl IntoResponse {
-    render_sync(&vec![], None)
+    render_sync(&vec![], &[], None)
 }
 
 fn render_diff(file_path: &str) -> Maybe<Option<Markup>> {
@@ -606,7 +875,62 @@ fn map_result_to_response<T>(res: Maybe<T>) -> impl IntoResponse {
 }
 
 async fn handle_merge() -> impl IntoResponse {
-    api_handler(merge_command)
+    api_handler(|ctx| merge_command(ctx, &MergeArgs::default()))
+}
+
+fn render_merge_report(report: &MergeQueueReport) -> Markup {
+    html! {
+        (DOCTYPE)
+        html {
+            head {
+                title {
+                    "Merge queue | itch ui"
+                }
+                (common_head_contents())
+            }
+            body.spaced-down {
+                h1 { "Merge queue" }
+
+                ul.spaced-down {
+                    @for (name, landing) in &report.results {
+                        li {
+                            @match landing {
+                                Landing::Landed => { (name) " landed." },
+                                Landing::Conflicted(paths) => {
+                                    (name) " stopped on conflicts in " (paths.join(", ")) "."
+                                },
+                                Landing::FailedVerification => { (name) " failed verification." },
+                            }
+                        }
+                    }
+                }
+
+                a href="/" {"Back"}
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct MergeQueueForm {
+    names: String,
+}
+
+async fn handle_merge_queue(Form(body): Form<MergeQueueForm>) -> impl IntoResponse {
+    let args = MergeArgs {
+        names: body.names.split_whitespace().map(String::from).collect(),
+        ..Default::default()
+    };
+    let queue_result = with_ctx(|ctx| {
+        let report = run_merge_queue(ctx, &args)?;
+        let landed: Vec<String> = report.landed().into_iter().map(String::from).collect();
+        cleanup_merged(ctx, &args, &landed)?;
+        Ok(report)
+    });
+    match queue_result {
+        Ok(report) => render_merge_report(&report).into_response(),
+        Err(e) => map_error_to_response(e).into_response(),
+    }
 }
 
 async fn handle_squash() -> impl IntoResponse {
@@ -640,6 +964,7 @@ async fn handle_save(Form(body): Form<SaveForm>) -> impl IntoResponse {
             ctx,
             &SaveArgs {
                 message: vec![body.message],
+                ..Default::default()
             },
             true,
         )
@@ -652,12 +977,8 @@ fn convert_sync_form(body: &SyncForm) -> Maybe<ResolutionMap> {
     let mut resolutions: ResolutionMap = HashMap::new();
 
     for (key, value) in body {
-        let value = if value == "incoming" {
-            ResolutionChoice::Incoming
-        } else if value == "base" {
-            ResolutionChoice::Base
-        } else if value == "later" {
-            ResolutionChoice::Later
+        let value = if let Some(choice) = ResolutionChoice::parse(value) {
+            choice
         } else if let Some(("manual", value)) = value.split_once(':') {
             ResolutionChoice::Manual(value.into())
         } else {
@@ -676,18 +997,36 @@ async fn handle_sync(Form(mut body): Form<SyncForm>) -> impl IntoResponse {
         let current_branch = get_current_branch(ctx)?;
         let target_branch = name.unwrap_or(current_branch);
         save_temp(ctx, "Save before sync".to_string())?;
-        let details = try_sync_branch(ctx, &target_branch, Some(&args))?;
+        let options = SyncOptions {
+            resolutions: Some(&args),
+            ..Default::default()
+        };
+        let report = try_sync_branch(ctx, &target_branch, &options)?;
         pop_and_reset(ctx)?;
-        Ok((target_branch, details))
+        Ok((target_branch, report))
     });
     match sync_result {
-        Ok((name, details)) => {
-            if let SyncDetails::Conflicted(d) = details {
-                render_sync(&d, Some(&name)).into_response()
-            } else {
-                Redirect::to("/").into_response()
+        Ok((name, report)) => match report.details {
+            SyncDetails::Conflicted(d) => {
+                render_sync(&d, &report.auto_resolved, Some(&name)).into_response()
             }
-        }
+            SyncDetails::Complete if !report.auto_resolved.is_empty() => html! {
+                (DOCTYPE)
+                html {
+                    head {
+                        title { "Synced | itch ui" }
+                        (common_head_contents())
+                    }
+                    body.spaced-down {
+                        h1 { "Synced" }
+                        (render_auto_resolved(&report.auto_resolved))
+                        a href="/" {"Back"}
+                    }
+                }
+            }
+            .into_response(),
+            SyncDetails::Complete => Redirect::to("/").into_response(),
+        },
         Err(e) => map_error_to_response(e).into_response(),
     }
 }
@@ -702,7 +1041,7 @@ async fn handle_sync_all() -> impl IntoResponse {
             if let Some(branch_name) = branch.name()?
                 && branch_name != "main"
             {
-                try_sync_branch(ctx, branch_name, None)?;
+                try_sync_branch(ctx, branch_name, &SyncOptions::default())?;
             }
         }
         pop_and_reset(ctx)?;
@@ -753,7 +1092,7 @@ async fn handle_delete(Form(body): Form<DeleteForm>) -> impl IntoResponse {
 }
 
 async fn handle_prune() -> impl IntoResponse {
-    api_handler(prune_command)
+    api_handler(|ctx| prune_command(ctx, &PruneArgs::default()))
 }
 
 async fn csrf_check<B>(
@@ -824,7 +1163,9 @@ async fn run_ui_server(ctx: &Ctx) -> Attempt {
 
     let api_router = Router::new()
         .route("/merge", post(handle_merge))
+        .route("/merge_queue", post(handle_merge_queue))
         .route("/squash", post(handle_squash))
+        .route("/history", post(handle_history))
         .route("/sync", post(handle_sync))
         .route("/sync_all", post(handle_sync_all))
         .route("/save", post(handle_save))
@@ -841,6 +1182,8 @@ async fn run_ui_server(ctx: &Ctx) -> Attempt {
     let root_router = Router::new()
         .route("/", get(dashboard))
         .route("/sync", get(sync))
+        .route("/conflicts", get(conflicts))
+        .route("/history", get(history))
         .route("/diff/*file_path", get(diff))
         .route("/_info", get(handle_info))
         .nest("/api", api_router)
@@ -0,0 +1,80 @@
+use git2::Oid;
+
+use crate::{
+    archive::{ArchiveTag, list_local_archive_tags, parse_archive_tag},
+    branch::local_branch_exists,
+    cli::UnarchiveArgs,
+    ctx::Ctx,
+    error::{Attempt, Maybe, fail},
+    print::show_warning,
+    remote::{delete_remote_refs, fetch_tag, get_remote_prefix, list_remote_refs, try_push_branch},
+};
+
+fn matches(tag: &ArchiveTag, name: &str) -> bool {
+    tag.tag == name || tag.branch == name
+}
+
+fn find_remote_archive(ctx: &Ctx, name: &str) -> Maybe<Option<(ArchiveTag, Oid)>> {
+    let prefix = format!("refs/tags/{}", get_remote_prefix()?);
+    Ok(list_remote_refs(ctx)?
+        .into_iter()
+        .filter_map(|(remote_name, id)| {
+            remote_name
+                .strip_prefix(&prefix)
+                .and_then(parse_archive_tag)
+                .map(|tag| (tag, id))
+        })
+        .filter(|(tag, _)| matches(tag, name))
+        .max_by_key(|(tag, _)| tag.timestamp))
+}
+
+pub fn unarchive_command(ctx: &Ctx, args: &UnarchiveArgs) -> Attempt {
+    let local = list_local_archive_tags(ctx)?
+        .into_iter()
+        .filter(|tag| matches(tag, &args.name))
+        .max_by_key(|tag| tag.timestamp);
+
+    let (tag, commit_id, is_local) = match local {
+        Some(tag) => {
+            let id = ctx
+                .repo
+                .find_reference(&format!("refs/tags/{}", tag.tag))?
+                .peel_to_commit()?
+                .id();
+            (tag, id, true)
+        }
+        None => match find_remote_archive(ctx, &args.name)? {
+            Some((tag, id)) => {
+                fetch_tag(ctx, &format!("{}{}", get_remote_prefix()?, tag.tag))?;
+                (tag, id, false)
+            }
+            None => return fail!("No archive found with that name."),
+        },
+    };
+
+    if local_branch_exists(ctx, &tag.branch)? {
+        return fail!(format!(
+            "A branch named {} already exists. Rename or delete it first.",
+            tag.branch
+        ));
+    }
+
+    let commit = ctx.repo.find_commit(commit_id)?;
+    ctx.repo.branch(&tag.branch, &commit, false)?;
+    try_push_branch(ctx, &tag.branch);
+
+    if is_local {
+        ctx.repo.tag_delete(&tag.tag)?;
+    }
+
+    let remote_ref = format!("refs/tags/{}{}", get_remote_prefix()?, tag.tag);
+    if let Err(e) = delete_remote_refs(ctx, &[remote_ref]) {
+        show_warning(ctx, &format!("Failed to delete archive on remote ({e})"));
+    }
+
+    if ctx.can_prompt() {
+        eprintln!("Restored {}", tag.branch);
+    }
+
+    Ok(())
+}
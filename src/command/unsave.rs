@@ -1,87 +1,28 @@
-use std::path::Path;
-
-use git2::{Commit, FileMode, ResetType, Tree, TreeEntry, build::TreeUpdateBuilder};
+use git2::{Commit, Repository, ResetType, Tree};
 
 use crate::{
     cli::UnsaveArgs,
+    commit::{find_save, list_saves, short_summary},
     ctx::Ctx,
-    error::{Attempt, fail},
+    error::{Attempt, Maybe, fail},
+    tree::{changed_paths, copy_paths},
 };
 
-const GIT_FILEMODE_UNREADABLE: i32 = 0o000_000;
-const GIT_FILEMODE_TREE: i32 = 0o040_000;
-const GIT_FILEMODE_BLOB: i32 = 0o100_644;
-const GIT_FILEMODE_BLOB_GROUP_WRITABLE: i32 = 0o100_664;
-const GIT_FILEMODE_BLOB_EXECUTABLE: i32 = 0o100_755;
-const GIT_FILEMODE_LINK: i32 = 0o120_000;
-const GIT_FILEMODE_COMMIT: i32 = 0o160_000;
-
-fn get_entry_mode(entry: &TreeEntry) -> FileMode {
-    let mode = entry.filemode();
-    if mode == GIT_FILEMODE_UNREADABLE {
-        return FileMode::Unreadable;
-    }
-    if mode == GIT_FILEMODE_TREE {
-        return FileMode::Tree;
-    }
-    if mode == GIT_FILEMODE_BLOB {
-        return FileMode::Blob;
-    }
-    if mode == GIT_FILEMODE_BLOB_GROUP_WRITABLE {
-        return FileMode::BlobGroupWritable;
-    }
-    if mode == GIT_FILEMODE_BLOB_EXECUTABLE {
-        return FileMode::BlobExecutable;
-    }
-    if mode == GIT_FILEMODE_LINK {
-        return FileMode::Link;
-    }
-    if mode == GIT_FILEMODE_COMMIT {
-        return FileMode::Commit;
-    }
-    FileMode::Unreadable
-}
-
-fn unsave_files(
-    ctx: &Ctx,
-    files: &[String],
-    head_commit: &Commit,
-    prev_commit: &Commit,
-) -> Attempt {
+fn unsave_files(ctx: &Ctx, files: &[String], head_commit: &Commit) -> Attempt {
     let current_tree = head_commit.tree()?;
-    let prev_tree = prev_commit.tree()?;
-
-    let mut new_tree_builder = TreeUpdateBuilder::new();
-
-    for file_path in files.iter().map(Path::new) {
-        match prev_tree.get_path(file_path) {
-            Ok(entry) => {
-                new_tree_builder.upsert(file_path, entry.id(), get_entry_mode(&entry));
-            }
-            Err(e) => match e.code() {
-                git2::ErrorCode::NotFound => {
-                    let should_delete = match current_tree.get_path(file_path) {
-                        Ok(_) => true,
-                        Err(e) => {
-                            if e.code() == git2::ErrorCode::NotFound {
-                                false
-                            } else {
-                                return Err(e.into());
-                            }
-                        }
-                    };
-                    if should_delete {
-                        new_tree_builder.remove(file_path);
-                    }
-                }
-                _ => return Err(e.into()),
-            },
-        }
+    let prev_tree = head_commit.parent(0)?.tree()?;
+
+    let paths = changed_paths(&ctx.repo, &prev_tree, &current_tree, files)?;
+    if paths.is_empty() {
+        return fail!(format!(
+            "The last save has no changes to {}.",
+            files.join(", ")
+        ));
     }
 
-    let new_tree: Tree = ctx
-        .repo
-        .find_tree(new_tree_builder.create_updated(&ctx.repo, &current_tree)?)?;
+    let new_tree: Tree =
+        ctx.repo
+            .find_tree(copy_paths(&ctx.repo, &paths, &prev_tree, &current_tree)?)?;
     let parents: Vec<Commit> = head_commit.parents().collect();
     let parent_refs: Vec<&Commit> = parents.iter().collect();
 
@@ -103,13 +44,44 @@ fn unsave_files(
     Ok(())
 }
 
-fn unsave_single(ctx: &Ctx, prev_commit: Commit) -> Attempt {
-    ctx.repo
-        .reset(&prev_commit.into_object(), ResetType::Mixed, None)?;
+fn unsave_to(ctx: &Ctx, target: &Commit) -> Attempt {
+    ctx.repo.reset(target.as_object(), ResetType::Mixed, None)?;
 
     Ok(())
 }
 
+fn unsave_count<'a>(saves: &[Commit<'a>], fork: Commit<'a>, count: usize) -> Maybe<Commit<'a>> {
+    match count {
+        0 => fail!("Expected at least one save to unsave."),
+        c if c < saves.len() => Ok(saves[c].clone()),
+        c if c == saves.len() => Ok(fork),
+        _ => fail!(format!(
+            "Cannot unsave {count} saves, there are only {} since the fork point.",
+            saves.len()
+        )),
+    }
+}
+
+fn names_path(repo: &Repository, head_commit: &Commit, arg: &str) -> Maybe<bool> {
+    if repo.workdir().is_some_and(|dir| dir.join(arg).exists()) {
+        return Ok(true);
+    }
+    let prev_tree = head_commit.parent(0)?.tree()?;
+    let changed = changed_paths(repo, &prev_tree, &head_commit.tree()?, &[arg.to_string()])?;
+    Ok(!changed.is_empty())
+}
+
+fn parse_count(repo: &Repository, head_commit: &Commit, args: &[String]) -> Maybe<Option<usize>> {
+    match args {
+        [] => Ok(Some(1)),
+        [arg] => match arg.parse::<usize>() {
+            Ok(count) if !names_path(repo, head_commit, arg)? => Ok(Some(count)),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
+
 pub fn unsave_command(ctx: &Ctx, args: &UnsaveArgs) -> Attempt {
     let head_commit = ctx.repo.head()?.peel_to_commit()?;
     let base_commit = ctx
@@ -126,16 +98,85 @@ pub fn unsave_command(ctx: &Ctx, args: &UnsaveArgs) -> Attempt {
         return fail!("Cannot unsave past the fork point");
     }
 
-    let mut parent_commits = head_commit.parents();
-    match (parent_commits.next(), parent_commits.next()) {
-        (None, _) => fail!("Latest commit has no parent."),
-        (Some(prev_commit), None) => {
-            if args.args.is_empty() {
-                unsave_single(ctx, prev_commit)
-            } else {
-                unsave_files(ctx, &args.args, &head_commit, &prev_commit)
+    if args.all {
+        if !args.args.is_empty() {
+            return fail!("Cannot combine --all with other arguments.");
+        }
+        return unsave_to(ctx, &fork_commit);
+    }
+
+    let saves = list_saves(ctx, &head_commit)?;
+
+    if let Some(count) = parse_count(&ctx.repo, &head_commit, &args.args)? {
+        return unsave_to(ctx, &unsave_count(&saves, fork_commit, count)?);
+    }
+
+    match args.args.as_slice() {
+        [to, query @ ..] if to == "to" && !query.is_empty() => {
+            let target = find_save(&saves, &query.join(" "))?;
+            if target.id() == head_commit.id() {
+                return fail!(format!(
+                    "{} is already the latest save.",
+                    short_summary(target)
+                ));
             }
+            unsave_to(ctx, target)
         }
-        (Some(_), Some(_)) => fail!("Expected single parent commit."),
+        files => unsave_files(ctx, files, &head_commit),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use git2::{Commit, Oid, Repository};
+
+    use super::parse_count;
+    use crate::diff::merge_tests::init_repo;
+
+    fn commit(repo: &Repository, parent: Option<&Commit>, files: &[(&str, &str)]) -> Oid {
+        let base = parent.map(|p| p.tree().unwrap());
+        let mut builder = repo.treebuilder(base.as_ref()).unwrap();
+        for (name, text) in files {
+            let blob = repo.blob(text.as_bytes()).unwrap();
+            builder.insert(name, blob, 0o100_644).unwrap();
+        }
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<&Commit> = parent.into_iter().collect();
+        repo.commit(None, &signature, &signature, "Save", &tree, &parents)
+            .unwrap()
+    }
+
+    fn parse(repo: &Repository, head: &Commit, arg: &str) -> Option<usize> {
+        parse_count(repo, head, &[arg.to_string()]).unwrap()
+    }
+
+    #[test]
+    fn parses_bare_numbers_as_counts() {
+        let (_dir, repo) = init_repo();
+        let base = repo
+            .find_commit(commit(&repo, None, &[("a.txt", "a\n")]))
+            .unwrap();
+        let head = repo
+            .find_commit(commit(&repo, Some(&base), &[("b.txt", "b\n")]))
+            .unwrap();
+        assert_eq!(parse_count(&repo, &head, &[]).unwrap(), Some(1));
+        assert_eq!(parse(&repo, &head, "3"), Some(3));
+        assert_eq!(parse(&repo, &head, "b.txt"), None);
+    }
+
+    #[test]
+    fn parses_numeric_paths_as_files() {
+        let (dir, repo) = init_repo();
+        let base = repo
+            .find_commit(commit(&repo, None, &[("a.txt", "a\n")]))
+            .unwrap();
+        let head = repo
+            .find_commit(commit(&repo, Some(&base), &[("2", "changed\n")]))
+            .unwrap();
+        std::fs::write(dir.path().join("3"), "untracked\n").unwrap();
+        assert_eq!(parse(&repo, &head, "2"), None);
+        assert_eq!(parse(&repo, &head, "3"), None);
+        assert_eq!(parse(&repo, &head, "4"), Some(4));
     }
 }
@@ -1,25 +1,80 @@
-use std::rc::Rc;
-
 use git2::Commit;
 
 use crate::{
     ctx::Ctx,
     error::{Maybe, fail},
+    reset::skip_temp_commits,
 };
 
-pub fn count_commits_since(_ctx: &Ctx, older: &Commit, newer: &Commit) -> Maybe<usize> {
-    let mut count: usize = 0;
-    let mut current = Rc::from(newer.clone());
-    while current.id() != older.id() {
-        let next = current.parents().next();
-        match next {
-            Some(c) => {
-                count += 1;
-                current = Rc::from(c);
-            }
-            None => return fail!("Unable to navigate to fork point."),
-        }
+pub fn count_commits_since(ctx: &Ctx, older: &Commit, newer: &Commit) -> Maybe<usize> {
+    let mut walk = ctx.repo.revwalk()?;
+    walk.push(newer.id())?;
+    walk.hide(older.id())?;
+    walk.try_fold(0, |count, id| id.map(|_| count + 1))
+        .map_err(std::convert::Into::into)
+}
+
+pub fn list_saves<'a>(ctx: &'a Ctx, tip: &Commit<'a>) -> Maybe<Vec<Commit<'a>>> {
+    let main = ctx
+        .repo
+        .find_branch("main", git2::BranchType::Local)?
+        .into_reference()
+        .peel_to_commit()?;
+    let fork_id = ctx.repo.merge_base(main.id(), tip.id())?;
+
+    let mut saves = vec![];
+    let mut commit = skip_temp_commits(tip.clone());
+    while commit.id() != fork_id && !ctx.repo.graph_descendant_of(fork_id, commit.id())? {
+        let parent = commit.parent(0)?;
+        saves.push(commit);
+        commit = parent;
     }
+    Ok(saves)
+}
 
-    Ok(count)
+pub fn short_summary(commit: &Commit) -> String {
+    format!(
+        "[{}] {}",
+        &commit.id().to_string()[0..8],
+        commit.summary().unwrap_or_default()
+    )
+}
+
+pub fn find_save<'a, 'b>(saves: &'b [Commit<'a>], query: &str) -> Maybe<&'b Commit<'a>> {
+    if let Ok(index) = query.parse::<usize>()
+        && (1..=saves.len()).contains(&index)
+    {
+        return Ok(&saves[index - 1]);
+    }
+
+    let query_lower = query.to_lowercase();
+    let by_id: Vec<&Commit> = saves
+        .iter()
+        .filter(|c| query.len() >= 4 && c.id().to_string().starts_with(&query_lower))
+        .collect();
+    let matches = if by_id.is_empty() {
+        saves
+            .iter()
+            .filter(|c| {
+                c.summary()
+                    .is_some_and(|m| m.to_lowercase().contains(&query_lower))
+            })
+            .collect()
+    } else {
+        by_id
+    };
+
+    match matches.as_slice() {
+        [save] => Ok(save),
+        [] => fail!(format!(
+            "No save matches {query}. Use its number from the latest (1), an id prefix, or words from its message."
+        )),
+        _ => {
+            let list: Vec<String> = matches.iter().map(|c| short_summary(c)).collect();
+            fail!(format!(
+                "{query} matches several saves:\n  {}",
+                list.join("\n  ")
+            ))
+        }
+    }
 }
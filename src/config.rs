@@ -0,0 +1,41 @@
+use clap::ValueEnum;
+use git2::ErrorCode;
+
+use crate::{
+    ctx::Ctx,
+    error::{Maybe, fail},
+};
+
+pub fn get_config_string(ctx: &Ctx, key: &str) -> Maybe<Option<String>> {
+    match ctx.repo.config()?.get_string(key) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if { e.code() == ErrorCode::NotFound } => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn get_config_bool(ctx: &Ctx, key: &str) -> Maybe<Option<bool>> {
+    match ctx.repo.config()?.get_bool(key) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if { e.code() == ErrorCode::NotFound } => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn get_config_i64(ctx: &Ctx, key: &str) -> Maybe<Option<i64>> {
+    match ctx.repo.config()?.get_i64(key) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if { e.code() == ErrorCode::NotFound } => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn get_config_enum<T: ValueEnum>(ctx: &Ctx, key: &str) -> Maybe<Option<T>> {
+    match get_config_string(ctx, key)? {
+        Some(v) => match T::from_str(&v, true) {
+            Ok(parsed) => Ok(Some(parsed)),
+            Err(_) => fail!(format!("Unrecognized value for {key}: {v}")),
+        },
+        None => Ok(None),
+    }
+}
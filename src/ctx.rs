@@ -49,3 +49,12 @@ pub fn init_ctx() -> Maybe<Ctx> {
         no_color: false,
     })
 }
+
+#[cfg(test)]
+pub fn test_ctx(repo: Repository) -> Ctx {
+    Ctx {
+        repo,
+        mode: Mode::Background,
+        no_color: true,
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use git2::Tree;
+
+use crate::{ctx::Ctx, diff::has_conflict_markers, error::Maybe, state::state_path};
+
+const DEFERRED_FILE: &str = "deferred";
+
+fn read_entries(ctx: &Ctx) -> Maybe<Vec<(String, String)>> {
+    let path = state_path(ctx, DEFERRED_FILE)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(branch, file)| (branch.to_string(), file.to_string()))
+        .collect())
+}
+
+fn write_entries(ctx: &Ctx, entries: &[(String, String)]) -> Maybe<()> {
+    let content = entries
+        .iter()
+        .map(|(branch, file)| [branch.as_str(), "\t", file.as_str(), "\n"].concat())
+        .collect::<String>();
+    std::fs::write(state_path(ctx, DEFERRED_FILE)?, content)?;
+    Ok(())
+}
+
+pub fn record_deferred(ctx: &Ctx, branch: &str, file: &str) -> Maybe<()> {
+    let mut entries = read_entries(ctx)?;
+    if !entries.iter().any(|(b, f)| b == branch && f == file) {
+        entries.push((branch.to_string(), file.to_string()));
+        write_entries(ctx, &entries)?;
+    }
+    Ok(())
+}
+
+pub fn clear_deferred(ctx: &Ctx, branch: &str, file: &str) -> Maybe<()> {
+    let mut entries = read_entries(ctx)?;
+    entries.retain(|(b, f)| !(b == branch && f == file));
+    write_entries(ctx, &entries)
+}
+
+pub fn list_deferred(ctx: &Ctx, branch: &str) -> Maybe<Vec<String>> {
+    Ok(read_entries(ctx)?
+        .into_iter()
+        .filter(|(b, _)| b == branch)
+        .map(|(_, f)| f)
+        .collect())
+}
+
+fn workdir_has_markers(ctx: &Ctx, file: &str) -> bool {
+    ctx.repo
+        .workdir()
+        .and_then(|dir| std::fs::read(dir.join(file)).ok())
+        .is_some_and(|content| has_conflict_markers(&content))
+}
+
+fn tree_has_markers(ctx: &Ctx, tree: &Tree, file: &str) -> bool {
+    tree.get_path(Path::new(file))
+        .and_then(|entry| ctx.repo.find_blob(entry.id()))
+        .is_ok_and(|blob| has_conflict_markers(blob.content()))
+}
+
+pub fn unresolved_in_workdir(ctx: &Ctx, branch: &str) -> Maybe<Vec<String>> {
+    let mut unresolved = vec![];
+    for file in list_deferred(ctx, branch)? {
+        if workdir_has_markers(ctx, &file) {
+            unresolved.push(file);
+        } else {
+            clear_deferred(ctx, branch, &file)?;
+        }
+    }
+    Ok(unresolved)
+}
+
+pub fn unresolved_in_tree(ctx: &Ctx, branch: &str, tree: &Tree) -> Maybe<Vec<String>> {
+    let mut unresolved = vec![];
+    for file in list_deferred(ctx, branch)? {
+        if tree_has_markers(ctx, tree, &file) {
+            unresolved.push(file);
+        }
+    }
+    Ok(unresolved)
+}
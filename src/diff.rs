@@ -1,7 +1,7 @@
-use git2::{Blob, Diff, DiffFindOptions, DiffLine, DiffOptions, Oid, Repository};
-use std::io::Read;
+use clap::ValueEnum;
+use git2::{Diff, DiffFindOptions, DiffLine, DiffOptions, Oid, Patch, Repository};
 
-use crate::error::{Attempt, Maybe, fail};
+use crate::error::{Attempt, Maybe, fail, inner_fail};
 
 pub fn split_diff_line(line: &DiffLine) -> (String, String) {
     let line = String::from_utf8_lossy(line.content());
@@ -28,33 +28,205 @@ pub fn good_diff_options() -> DiffOptions {
     options
 }
 
-fn oid_to_string<'a>(repo: &'a Repository, oid: &git2::Oid) -> Maybe<(Option<Blob<'a>>, String)> {
+pub fn has_conflict_markers(content: &[u8]) -> bool {
+    let mut open = false;
+    for line in decode_text(content).lines() {
+        if line.starts_with("<<<<<<<") {
+            open = true;
+        } else if open && line.starts_with(">>>>>>>") {
+            return true;
+        }
+    }
+    false
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+pub fn detect_encoding(bytes: &[u8]) -> TextEncoding {
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        TextEncoding::Utf16Le
+    } else if bytes.starts_with(&[0xFE, 0xFF]) {
+        TextEncoding::Utf16Be
+    } else if std::str::from_utf8(bytes).is_ok() {
+        TextEncoding::Utf8
+    } else {
+        TextEncoding::Latin1
+    }
+}
+
+pub fn is_mergeable_text(bytes: &[u8]) -> bool {
+    match detect_encoding(bytes) {
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => true,
+        TextEncoding::Utf8 | TextEncoding::Latin1 => !bytes.contains(&0),
+    }
+}
+
+pub fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |to_unit: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|pair| to_unit([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    match detect_encoding(bytes) {
+        TextEncoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        TextEncoding::Utf16Le => utf16(u16::from_le_bytes),
+        TextEncoding::Utf16Be => utf16(u16::from_be_bytes),
+        TextEncoding::Latin1 => bytes.iter().map(|b| char::from(*b)).collect(),
+    }
+}
+
+pub fn encode_text(text: &str, encoding: TextEncoding) -> Maybe<Vec<u8>> {
+    Ok(match encoding {
+        TextEncoding::Utf8 => text.as_bytes().to_vec(),
+        TextEncoding::Utf16Le => [0xFF, 0xFE]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect(),
+        TextEncoding::Utf16Be => [0xFE, 0xFF]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect(),
+        TextEncoding::Latin1 => {
+            let mut bytes = vec![];
+            for c in text.chars() {
+                match u8::try_from(c) {
+                    Ok(b) => bytes.push(b),
+                    Err(_) => return fail!(format!("'{c}' cannot be written as Latin-1.")),
+                }
+            }
+            bytes
+        }
+    })
+}
+
+fn read_blob(repo: &Repository, oid: &Oid) -> Maybe<Vec<u8>> {
     if oid.is_zero() {
-        return Ok((None, String::new()));
+        return Ok(vec![]);
     }
     let blob = repo.find_blob(*oid)?;
-    if blob.is_binary() {
+    if !is_mergeable_text(blob.content()) {
         return fail!("Cannot load binary data line-by-line");
     }
-    let mut original_content = String::new();
-    blob.content()
-        .read_to_string(&mut original_content)
-        .unwrap();
-    Ok((Some(blob), original_content))
+    Ok(blob.content().to_vec())
+}
+
+fn get_lines(bytes: &[u8]) -> Vec<&[u8]> {
+    bytes.split_inclusive(|b| *b == b'\n').collect()
+}
+
+fn line_ending(sides: &[&[&[u8]]]) -> &'static [u8] {
+    if sides
+        .iter()
+        .any(|lines| lines.iter().any(|line| line.ends_with(b"\r\n")))
+    {
+        b"\r\n"
+    } else {
+        b"\n"
+    }
+}
+
+fn slice_lines<'a>(source: &'a [&'a [u8]], range: &Range) -> Maybe<&'a [&'a [u8]]> {
+    source
+        .get(range.0..range.1)
+        .ok_or_else(|| inner_fail!("Diff hunk is outside of the file."))
 }
 
-fn get_lines(str: &str) -> Vec<&str> {
-    let mut original_lines: Vec<&str> = str.split_inclusive('\n').collect();
-    if original_lines.last() == Some(&"") {
-        original_lines.pop();
+fn copy_lines(target: &mut Vec<u8>, source: &[&[u8]], range: &Range) -> Attempt {
+    for line in slice_lines(source, range)? {
+        target.extend_from_slice(line);
     }
-    original_lines
+    Ok(())
 }
 
-fn copy_lines(target: &mut String, source: &[&str], range: &Range) {
-    for line in &source[range.0..range.1] {
-        target.push_str(line);
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ConflictStyle {
+    #[default]
+    Merge,
+    Diff3,
+    Zdiff3,
+}
+
+#[derive(Default)]
+pub struct ConflictMarkers {
+    pub style: ConflictStyle,
+    pub main_label: Option<String>,
+    pub base_label: Option<String>,
+    pub branch_label: Option<String>,
+}
+
+enum MergeMode<'a> {
+    Markers(&'a ConflictMarkers),
+    Union,
+    Clean,
+}
+
+fn push_marker(target: &mut Vec<u8>, marker: &[u8], label: Option<&String>, eol: &[u8]) {
+    if !target.is_empty() && !target.ends_with(b"\n") {
+        target.extend_from_slice(eol);
     }
+    target.extend_from_slice(marker);
+    if let Some(label) = label {
+        target.push(b' ');
+        target.extend_from_slice(label.as_bytes());
+    }
+    target.extend_from_slice(eol);
+}
+
+fn common_affixes(upstream: &[&[u8]], branch: &[&[u8]]) -> (usize, usize) {
+    let shortest = upstream.len().min(branch.len());
+    let prefix = (0..shortest)
+        .take_while(|i| upstream[*i] == branch[*i])
+        .count();
+    let suffix = (0..shortest - prefix)
+        .take_while(|i| upstream[upstream.len() - 1 - i] == branch[branch.len() - 1 - i])
+        .count();
+    (prefix, suffix)
+}
+
+fn push_conflict(
+    target: &mut Vec<u8>,
+    markers: &ConflictMarkers,
+    upstream: &[&[u8]],
+    base: &[&[u8]],
+    branch: &[&[u8]],
+    eol: &[u8],
+) {
+    if upstream == branch {
+        target.extend(upstream.concat());
+        return;
+    }
+
+    let (prefix, suffix) = match markers.style {
+        ConflictStyle::Zdiff3 => common_affixes(upstream, branch),
+        ConflictStyle::Merge | ConflictStyle::Diff3 => (0, 0),
+    };
+
+    target.extend(upstream[..prefix].concat());
+
+    if prefix + suffix == upstream.len() && upstream.len() == branch.len() {
+        return;
+    }
+
+    push_marker(target, b"<<<<<<<", markers.main_label.as_ref(), eol);
+    target.extend(upstream[prefix..upstream.len() - suffix].concat());
+    if markers.style != ConflictStyle::Merge {
+        push_marker(target, b"|||||||", markers.base_label.as_ref(), eol);
+        target.extend(base.concat());
+    }
+    push_marker(target, b"=======", None, eol);
+    target.extend(branch[prefix..branch.len() - suffix].concat());
+    push_marker(target, b">>>>>>>", markers.branch_label.as_ref(), eol);
+
+    target.extend(upstream[upstream.len() - suffix..].concat());
 }
 
 #[derive(Debug, Clone)]
@@ -106,71 +278,71 @@ struct MyHunk {
     new: Range,
 }
 
-fn get_diff_hunks(
-    repo: &Repository,
-    old_blob: Option<&git2::Blob>,
-    new_blob: Option<&git2::Blob>,
-) -> Vec<MyHunk> {
-    let old_as_path = None;
-    let new_as_path = None;
+fn get_diff_hunks(old: &[u8], new: &[u8]) -> Maybe<Vec<MyHunk>> {
     let mut opts = DiffOptions::new();
     opts.context_lines(0);
     opts.ignore_whitespace(true);
-    let file_cb = None;
-    let binary_cb = None;
-    let line_cb = None;
+    opts.force_text(true);
 
-    let mut res = vec![];
+    let patch = Patch::from_buffers(old, None, new, None, Some(&mut opts))?;
 
-    repo.diff_blobs(
-        old_blob,
-        old_as_path,
-        new_blob,
-        new_as_path,
-        Some(&mut opts),
-        file_cb,
-        binary_cb,
-        Some(&mut |_, hunk| {
-            res.push(MyHunk {
-                old: Range::from_indices(hunk.old_start(), hunk.old_lines()),
-                new: Range::from_indices(hunk.new_start(), hunk.new_lines()),
-            });
-            true
-        }),
-        line_cb,
-    )
-    .unwrap();
+    let mut res = vec![];
+    for i in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(i)?;
+        res.push(MyHunk {
+            old: Range::from_indices(hunk.old_start(), hunk.old_lines()),
+            new: Range::from_indices(hunk.new_start(), hunk.new_lines()),
+        });
+    }
 
-    res
+    Ok(res)
 }
 
-pub fn get_merge_text(
-    repo: &Repository,
-    original_id: &Oid,
-    upstream_id: &Oid,
-    branch_id: &Oid,
-) -> Maybe<String> {
-    let (original_blob, original_string) = oid_to_string(repo, original_id)?;
-    let original_lines = get_lines(&original_string);
+pub fn similarity(repo: &Repository, old_id: &Oid, new_id: &Oid) -> Maybe<usize> {
+    if old_id == new_id {
+        return Ok(100);
+    }
 
-    let (upstream_blob, upstream_string) = oid_to_string(repo, upstream_id)?;
-    let upstream_lines = get_lines(&upstream_string);
+    let old = repo.find_blob(*old_id)?;
+    let new = repo.find_blob(*new_id)?;
+    if !is_mergeable_text(old.content()) || !is_mergeable_text(new.content()) {
+        return Ok(0);
+    }
 
-    let (branch_blob, branch_string) = oid_to_string(repo, branch_id)?;
-    let branch_lines = get_lines(&branch_string);
+    let old_lines = get_lines(old.content()).len();
+    let longest = old_lines.max(get_lines(new.content()).len());
+    if longest == 0 {
+        return Ok(100);
+    }
 
-    let mut branch_hunks = get_diff_hunks(repo, original_blob.as_ref(), branch_blob.as_ref())
-        .into_iter()
-        .peekable();
-    let mut upstream_hunks = get_diff_hunks(repo, original_blob.as_ref(), upstream_blob.as_ref())
-        .into_iter()
-        .peekable();
+    let removed: usize = get_diff_hunks(old.content(), new.content())?
+        .iter()
+        .map(|hunk| hunk.old.1 - hunk.old.0)
+        .sum();
+
+    Ok(old_lines.saturating_sub(removed) * 100 / longest)
+}
+
+fn merge_lines(
+    original: &[u8],
+    upstream: &[u8],
+    branch: &[u8],
+    mode: &MergeMode,
+) -> Maybe<Option<Vec<u8>>> {
+    let original_lines = get_lines(original);
+    let upstream_lines = get_lines(upstream);
+    let branch_lines = get_lines(branch);
+
+    let eol = line_ending(&[&upstream_lines, &branch_lines]);
+
+    let mut branch_hunks = get_diff_hunks(original, branch)?.into_iter().peekable();
+    let mut upstream_hunks = get_diff_hunks(original, upstream)?.into_iter().peekable();
 
     let mut original_index: usize = 0;
 
-    let mut res = String::new();
+    let mut res: Vec<u8> = vec![];
 
-    for _ in 0..1_000 {
+    loop {
         let mut original_range: Range = match (upstream_hunks.peek(), branch_hunks.peek()) {
             (Some(upstream), Some(branch)) => {
                 if upstream.old.0 < branch.old.0 {
@@ -210,47 +382,155 @@ pub fn get_merge_text(
             }
         }
 
-        for line in &original_lines[original_index..original_range.0] {
-            res.push_str(line);
-        }
+        copy_lines(
+            &mut res,
+            &original_lines,
+            &Range(original_index, original_range.0),
+        )?;
 
         match (upstream_range, branch_range) {
-            (Some(upstream_range), Some(branch_range)) => {
-                res.push_str("<<<<<<<\n");
-                copy_lines(&mut res, &upstream_lines, &upstream_range);
-                res.push_str("=======\n");
-                copy_lines(&mut res, &branch_lines, &branch_range);
-                res.push_str(">>>>>>>\n");
-            }
+            (Some(upstream_range), Some(branch_range)) => match mode {
+                MergeMode::Union => {
+                    copy_lines(&mut res, &upstream_lines, &upstream_range)?;
+                    copy_lines(&mut res, &branch_lines, &branch_range)?;
+                }
+                MergeMode::Markers(markers) => push_conflict(
+                    &mut res,
+                    markers,
+                    slice_lines(&upstream_lines, &upstream_range)?,
+                    slice_lines(&original_lines, &original_range)?,
+                    slice_lines(&branch_lines, &branch_range)?,
+                    eol,
+                ),
+                MergeMode::Clean => {
+                    let upstream_slice = slice_lines(&upstream_lines, &upstream_range)?;
+                    if upstream_slice != slice_lines(&branch_lines, &branch_range)? {
+                        return Ok(None);
+                    }
+                    res.extend(upstream_slice.concat());
+                }
+            },
             (Some(upstream_range), None) => {
-                copy_lines(&mut res, &upstream_lines, &upstream_range);
+                copy_lines(&mut res, &upstream_lines, &upstream_range)?;
             }
             (None, Some(branch_range)) => {
-                copy_lines(&mut res, &branch_lines, &branch_range);
+                copy_lines(&mut res, &branch_lines, &branch_range)?;
             }
             (None, None) => {
-                panic!("Should always have upstream or branch range.");
+                return fail!("Merge hunk has no upstream or branch lines.");
             }
         }
 
         original_index = original_range.1;
     }
 
-    {
-        for line in &original_lines[original_index..] {
-            res.push_str(line);
-        }
-    }
+    copy_lines(
+        &mut res,
+        &original_lines,
+        &Range(original_index, original_lines.len()),
+    )?;
 
-    Ok(res)
+    Ok(Some(res))
+}
+
+fn build_merge_bytes(
+    repo: &Repository,
+    original_id: &Oid,
+    upstream_id: &Oid,
+    branch_id: &Oid,
+    mode: &MergeMode,
+) -> Maybe<Option<Vec<u8>>> {
+    let original = read_blob(repo, original_id)?;
+    let upstream = read_blob(repo, upstream_id)?;
+    let branch = read_blob(repo, branch_id)?;
+
+    let encodings = [&original, &upstream, &branch].map(|bytes| detect_encoding(bytes));
+
+    let Some(utf16) = encodings
+        .into_iter()
+        .find(|e| matches!(e, TextEncoding::Utf16Le | TextEncoding::Utf16Be))
+    else {
+        return merge_lines(&original, &upstream, &branch, mode);
+    };
+
+    let merged = merge_lines(
+        decode_text(&original).as_bytes(),
+        decode_text(&upstream).as_bytes(),
+        decode_text(&branch).as_bytes(),
+        mode,
+    )?;
+
+    merged
+        .map(|merged| encode_text(&String::from_utf8_lossy(&merged), utf16))
+        .transpose()
+}
+
+pub fn get_merge_bytes(
+    repo: &Repository,
+    original_id: &Oid,
+    upstream_id: &Oid,
+    branch_id: &Oid,
+    markers: &ConflictMarkers,
+) -> Maybe<Vec<u8>> {
+    Ok(build_merge_bytes(
+        repo,
+        original_id,
+        upstream_id,
+        branch_id,
+        &MergeMode::Markers(markers),
+    )?
+    .unwrap_or_default())
+}
+
+pub fn get_merge_text(
+    repo: &Repository,
+    original_id: &Oid,
+    upstream_id: &Oid,
+    branch_id: &Oid,
+    markers: &ConflictMarkers,
+) -> Maybe<String> {
+    Ok(decode_text(&get_merge_bytes(
+        repo,
+        original_id,
+        upstream_id,
+        branch_id,
+        markers,
+    )?))
+}
+
+pub fn get_union_text(
+    repo: &Repository,
+    original_id: &Oid,
+    upstream_id: &Oid,
+    branch_id: &Oid,
+) -> Maybe<String> {
+    Ok(decode_text(
+        &build_merge_bytes(repo, original_id, upstream_id, branch_id, &MergeMode::Union)?
+            .unwrap_or_default(),
+    ))
+}
+
+pub fn get_clean_merge_text(
+    repo: &Repository,
+    original_id: &Oid,
+    upstream_id: &Oid,
+    branch_id: &Oid,
+) -> Maybe<Option<String>> {
+    Ok(
+        build_merge_bytes(repo, original_id, upstream_id, branch_id, &MergeMode::Clean)?
+            .map(|merged| decode_text(&merged)),
+    )
 }
 
 #[cfg(test)]
-mod merge_tests {
+pub(crate) mod merge_tests {
     use git2::{Oid, Repository};
     use tempfile::TempDir;
 
-    use crate::diff::get_merge_text;
+    use crate::diff::{
+        ConflictMarkers, ConflictStyle, TextEncoding, decode_text, encode_text,
+        get_clean_merge_text, get_merge_bytes, get_merge_text, get_union_text, similarity,
+    };
 
     pub fn init_repo() -> (TempDir, Repository) {
         let dir = tempfile::tempdir().unwrap();
@@ -263,7 +543,14 @@ mod merge_tests {
         let original_id = repo.blob(original.as_bytes()).unwrap();
         let upstream_id = repo.blob(upstream.as_bytes()).unwrap();
         let branch_id = repo.blob(branch.as_bytes()).unwrap();
-        let res = get_merge_text(&repo, &original_id, &upstream_id, &branch_id).unwrap();
+        let res = get_merge_text(
+            &repo,
+            &original_id,
+            &upstream_id,
+            &branch_id,
+            &ConflictMarkers::default(),
+        )
+        .unwrap();
         drop(dir);
         res
     }
@@ -445,13 +732,281 @@ return 2;
         );
     }
 
+    fn merge_bytes(original: &[u8], upstream: &[u8], branch: &[u8]) -> Vec<u8> {
+        let (_dir, repo) = init_repo();
+        let original_id = repo.blob(original).unwrap();
+        let upstream_id = repo.blob(upstream).unwrap();
+        let branch_id = repo.blob(branch).unwrap();
+        get_merge_bytes(
+            &repo,
+            &original_id,
+            &upstream_id,
+            &branch_id,
+            &ConflictMarkers::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn keeps_crlf() {
+        let combined = merge_bytes(b"same\r\n", b"same\r\nupstream\r\n", b"same\r\nbranch\r\n");
+        assert_eq!(
+            combined,
+            b"same\r\n<<<<<<<\r\nupstream\r\n=======\r\nbranch\r\n>>>>>>>\r\n"
+        );
+    }
+
+    #[test]
+    fn keeps_latin1_bytes() {
+        let combined = merge_bytes(
+            b"caf\xe9\na\nx\ny\nb\n",
+            b"caf\xe9\nA\nx\ny\nb\n",
+            b"caf\xe9\na\nx\ny\nB\xe9\n",
+        );
+        assert_eq!(combined, b"caf\xe9\nA\nx\ny\nB\xe9\n");
+        assert_eq!(
+            encode_text(&decode_text(&combined), TextEncoding::Latin1).unwrap(),
+            combined
+        );
+    }
+
+    #[test]
+    fn rejects_text_outside_latin1() {
+        assert_eq!(
+            encode_text("caf\u{e9}\n", TextEncoding::Latin1).unwrap(),
+            b"caf\xe9\n"
+        );
+        assert!(encode_text("caf\u{e9} \u{2192} \u{1f600}\n", TextEncoding::Latin1).is_err());
+    }
+
+    #[test]
+    fn merges_utf16() {
+        let encode = |text: &str| encode_text(text, TextEncoding::Utf16Le).unwrap();
+        let combined = merge_bytes(
+            &encode("é\na\nx\ny\nb\n"),
+            &encode("é\nA\nx\ny\nb\n"),
+            &encode("é\na\nx\ny\nB\n"),
+        );
+        assert_eq!(combined, encode("é\nA\nx\ny\nB\n"));
+    }
+
+    #[test]
+    fn marker_on_new_line_at_eof() {
+        let combined = merge_files("same\n", "same\nupstream", "same\nbranch");
+        assert_eq!(
+            &combined,
+            "same\n<<<<<<<\nupstream\n=======\nbranch\n>>>>>>>\n"
+        );
+    }
+
+    fn merge_with_style(
+        original: &str,
+        upstream: &str,
+        branch: &str,
+        markers: &ConflictMarkers,
+    ) -> String {
+        let (_dir, repo) = init_repo();
+        let original_id = repo.blob(original.as_bytes()).unwrap();
+        let upstream_id = repo.blob(upstream.as_bytes()).unwrap();
+        let branch_id = repo.blob(branch.as_bytes()).unwrap();
+        get_merge_text(&repo, &original_id, &upstream_id, &branch_id, markers).unwrap()
+    }
+
+    #[test]
+    fn diff3_shows_base() {
+        let markers = ConflictMarkers {
+            style: ConflictStyle::Diff3,
+            ..Default::default()
+        };
+        let combined = merge_with_style("a\nold\n", "a\nup\n", "a\nbr\n", &markers);
+        assert_eq!(
+            &combined,
+            "a\n<<<<<<<\nup\n|||||||\nold\n=======\nbr\n>>>>>>>\n"
+        );
+    }
+
+    #[test]
+    fn zdiff3_moves_common_lines_out() {
+        let markers = ConflictMarkers {
+            style: ConflictStyle::Zdiff3,
+            ..Default::default()
+        };
+        let combined = merge_with_style(
+            "a\nold\n",
+            "a\nsame\nup\nend\n",
+            "a\nsame\nbr\nend\n",
+            &markers,
+        );
+        assert_eq!(
+            &combined,
+            "a\nsame\n<<<<<<<\nup\n|||||||\nold\n=======\nbr\n>>>>>>>\nend\n"
+        );
+    }
+
+    fn merge_identical_changes(style: ConflictStyle) -> String {
+        let markers = ConflictMarkers {
+            style,
+            ..Default::default()
+        };
+        merge_with_style("a\nold\nz\n", "a\nnew\nz\n", "a\nnew\nz\n", &markers)
+    }
+
+    #[test]
+    fn merge_style_takes_identical_changes() {
+        assert_eq!(merge_identical_changes(ConflictStyle::Merge), "a\nnew\nz\n");
+    }
+
+    #[test]
+    fn diff3_takes_identical_changes() {
+        assert_eq!(merge_identical_changes(ConflictStyle::Diff3), "a\nnew\nz\n");
+    }
+
+    #[test]
+    fn zdiff3_takes_identical_changes() {
+        assert_eq!(
+            merge_identical_changes(ConflictStyle::Zdiff3),
+            "a\nnew\nz\n"
+        );
+    }
+
+    #[test]
+    fn clean_merge_only_without_conflicts() {
+        let (_dir, repo) = init_repo();
+        let blob = |text: &str| repo.blob(text.as_bytes()).unwrap();
+        let base = blob("a\nb\nc\nd\ne\n");
+        assert_eq!(
+            get_clean_merge_text(
+                &repo,
+                &base,
+                &blob("A\nb\nc\nd\ne\n"),
+                &blob("a\nb\nc\nd\nE\n")
+            )
+            .unwrap()
+            .as_deref(),
+            Some("A\nb\nc\nd\nE\n")
+        );
+        assert!(
+            get_clean_merge_text(
+                &repo,
+                &base,
+                &blob("A\nb\nc\nd\ne\n"),
+                &blob("x\nb\nc\nd\ne\n")
+            )
+            .unwrap()
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn labels_markers() {
+        let markers = ConflictMarkers {
+            style: ConflictStyle::Merge,
+            main_label: Some("main (abc1234)".to_string()),
+            base_label: Some("base".to_string()),
+            branch_label: Some("feat (def5678 Save)".to_string()),
+        };
+        let combined = merge_with_style("a\n", "a\nup\n", "a\nbr\n", &markers);
+        assert_eq!(
+            &combined,
+            "a\n<<<<<<< main (abc1234)\nup\n=======\nbr\n>>>>>>> feat (def5678 Save)\n"
+        );
+    }
+
+    fn generated_sides(lines: usize) -> (String, String, String) {
+        use std::fmt::Write;
+
+        let mut original = String::new();
+        let mut upstream = String::new();
+        let mut branch = String::new();
+        for i in 0..lines {
+            let line = format!("line {i}\n");
+            original.push_str(&line);
+            match i % 6 {
+                1 => writeln!(upstream, "upstream {i}").unwrap(),
+                _ => upstream.push_str(&line),
+            }
+            match i % 6 {
+                4 => writeln!(branch, "branch {i}").unwrap(),
+                _ => branch.push_str(&line),
+            }
+        }
+        (original, upstream, branch)
+    }
+
+    #[test]
+    fn merges_many_hunks() {
+        let (original, upstream, branch) = generated_sides(12_000);
+        let combined = merge_files(&original, &upstream, &branch);
+        assert_eq!(combined.lines().count(), 12_000);
+        assert!(combined.ends_with("line 11999\n"));
+        assert!(combined.contains("upstream 11995\n"));
+        assert!(combined.contains("branch 11998\n"));
+        assert!(!combined.contains("<<<<<<<"));
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored bench_`"]
+    fn bench_merge_scaling() {
+        for lines in [10_000, 100_000, 400_000] {
+            let (original, upstream, branch) = generated_sides(lines);
+            let start = std::time::Instant::now();
+            let combined = merge_files(&original, &upstream, &branch);
+            let elapsed = start.elapsed();
+            assert_eq!(combined.lines().count(), lines);
+            println!("{lines} lines, {} hunks: {elapsed:?}", lines / 3);
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored bench_`"]
+    fn bench_merge_conflicts() {
+        for lines in [10_000, 100_000] {
+            let (original, upstream, _) = generated_sides(lines);
+            let branch = upstream.replace("upstream", "branch");
+            let start = std::time::Instant::now();
+            let combined = merge_files(&original, &upstream, &branch);
+            let elapsed = start.elapsed();
+            let conflicts = upstream.matches("upstream").count();
+            assert_eq!(combined.matches("<<<<<<<").count(), conflicts);
+            println!("{lines} lines, {conflicts} conflicts: {elapsed:?}");
+        }
+    }
+
+    #[test]
+    fn similarity_of_edited_file() {
+        let (_dir, repo) = init_repo();
+        let original = repo.blob(b"1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n").unwrap();
+        let edited = repo.blob(b"1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n").unwrap();
+        let other = repo.blob(b"a\nb\n").unwrap();
+        assert_eq!(similarity(&repo, &original, &original).unwrap(), 100);
+        assert_eq!(similarity(&repo, &original, &edited).unwrap(), 90);
+        assert_eq!(similarity(&repo, &original, &other).unwrap(), 0);
+    }
+
+    #[test]
+    fn union_conflicting_line() {
+        let (_dir, repo) = init_repo();
+        let original_id = repo.blob(b"same\n").unwrap();
+        let upstream_id = repo.blob(b"same\nupstream\n").unwrap();
+        let branch_id = repo.blob(b"same\nbranch\n").unwrap();
+        let combined = get_union_text(&repo, &original_id, &upstream_id, &branch_id).unwrap();
+        assert_eq!(&combined, "same\nupstream\nbranch\n");
+    }
+
     #[test]
     fn no_original() {
         let (dir, repo) = init_repo();
         let original_id = Oid::zero();
         let upstream_id = repo.blob("upstream content\n".as_bytes()).unwrap();
         let branch_id = repo.blob("branch content\n".as_bytes()).unwrap();
-        let combined = get_merge_text(&repo, &original_id, &upstream_id, &branch_id).unwrap();
+        let combined = get_merge_text(
+            &repo,
+            &original_id,
+            &upstream_id,
+            &branch_id,
+            &ConflictMarkers::default(),
+        )
+        .unwrap();
         assert_eq!(
             &combined,
             "<<<<<<<\nupstream content\n=======\nbranch content\n>>>>>>>\n"
@@ -465,7 +1020,14 @@ return 2;
         let original_id = repo.blob("original content\n".as_bytes()).unwrap();
         let upstream_id = repo.blob("upstream content\n".as_bytes()).unwrap();
         let branch_id = Oid::zero();
-        let combined = get_merge_text(&repo, &original_id, &upstream_id, &branch_id).unwrap();
+        let combined = get_merge_text(
+            &repo,
+            &original_id,
+            &upstream_id,
+            &branch_id,
+            &ConflictMarkers::default(),
+        )
+        .unwrap();
         assert_eq!(&combined, "<<<<<<<\nupstream content\n=======\n>>>>>>>\n");
         drop(dir);
     }
@@ -476,12 +1038,41 @@ return 2;
         let original_id = repo.blob("original content\n".as_bytes()).unwrap();
         let upstream_id = Oid::zero();
         let branch_id = repo.blob("branch content\n".as_bytes()).unwrap();
-        let combined = get_merge_text(&repo, &original_id, &upstream_id, &branch_id).unwrap();
+        let combined = get_merge_text(
+            &repo,
+            &original_id,
+            &upstream_id,
+            &branch_id,
+            &ConflictMarkers::default(),
+        )
+        .unwrap();
         assert_eq!(&combined, "<<<<<<<\n=======\nbranch content\n>>>>>>>\n");
         drop(dir);
     }
 }
 
+#[cfg(test)]
+mod marker_tests {
+    use super::has_conflict_markers;
+
+    #[test]
+    fn finds_markers() {
+        assert!(has_conflict_markers(
+            b"a\n<<<<<<<\nb\n=======\nc\n>>>>>>>\n"
+        ));
+    }
+
+    #[test]
+    fn ignores_clean_text() {
+        assert!(!has_conflict_markers(b"a\nb\n=======\n"));
+    }
+
+    #[test]
+    fn requires_closing_marker() {
+        assert!(!has_conflict_markers(b"<<<<<<< not really\n"));
+    }
+}
+
 #[cfg(test)]
 mod range_tests {
     use super::Range;
@@ -1,28 +1,18 @@
-use std::ffi::{OsStr, OsString};
+use std::{
+    ffi::{OsStr, OsString},
+    path::Path,
+};
 
-use crate::error::{Maybe, fail, inner_fail};
-
-pub fn edit_temp_text(initial_content: &str, extension: Option<&OsStr>) -> Maybe<String> {
-    let mut temp_path = std::env::temp_dir();
-    let mut filename: OsString = "itch_edit_buffer.".into();
-    if let Some(ext) = extension {
-        filename.push(ext);
-    } else {
-        filename.push("txt");
-    }
-
-    temp_path.push(filename);
-
-    std::fs::write(&temp_path, initial_content)?;
+use crate::error::{Attempt, Maybe, fail, inner_fail};
 
+pub fn edit_file(path: &Path) -> Attempt {
     let editor_command = match std::env::var("EDITOR") {
         Ok(v) => std::process::Command::new("sh")
             .arg("-c")
-            .arg(format!("{} {}", v, temp_path.to_string_lossy()))
+            .arg(format!("{v} \"$0\""))
+            .arg(path)
             .spawn(),
-        Err(std::env::VarError::NotPresent) => {
-            std::process::Command::new("vim").arg(&temp_path).spawn()
-        }
+        Err(std::env::VarError::NotPresent) => std::process::Command::new("vim").arg(path).spawn(),
         Err(_) => {
             return fail!("Unexpected error reading EDITOR variable");
         }
@@ -38,6 +28,24 @@ pub fn edit_temp_text(initial_content: &str, extension: Option<&OsStr>) -> Maybe
         return fail!("Edit session exited with failure.");
     }
 
+    Ok(())
+}
+
+pub fn edit_temp_text(initial_content: &str, extension: Option<&OsStr>) -> Maybe<String> {
+    let mut temp_path = std::env::temp_dir();
+    let mut filename: OsString = "itch_edit_buffer.".into();
+    if let Some(ext) = extension {
+        filename.push(ext);
+    } else {
+        filename.push("txt");
+    }
+
+    temp_path.push(filename);
+
+    std::fs::write(&temp_path, initial_content)?;
+
+    edit_file(&temp_path)?;
+
     let res = std::fs::read_to_string(&temp_path)
         .map_err(|_| inner_fail!("Failed to read file after edit."))?;
 
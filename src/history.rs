@@ -0,0 +1,252 @@
+use git2::Oid;
+
+use crate::error::{Maybe, fail};
+
+const PLAN_HELP: &str = "
+# Edit the saves on this branch, oldest first. Lines run from top to bottom.
+#
+# pick   keep the save as it is
+# reword keep the save, using the message written on this line
+# fold   combine the save into the one above it, keeping that message
+# drop   remove the save and its changes
+# split  split the save into several saves by hunk
+#
+# Lines can be reordered. Removing a line drops the save.
+# If the list is empty, nothing is changed.
+";
+
+const HUNKS_HELP: &str = "
+# Give each hunk the number of the save it should go into.
+# Saves are created in number order and keep the original message,
+# with (1/2), (2/2) and so on added to the first line.
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlanAction {
+    Pick,
+    Reword,
+    Fold,
+    Drop,
+    Split,
+}
+
+impl PlanAction {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "pick" | "p" => Some(Self::Pick),
+            "reword" | "r" => Some(Self::Reword),
+            "fold" | "f" => Some(Self::Fold),
+            "drop" | "d" => Some(Self::Drop),
+            "split" | "s" => Some(Self::Split),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pick => "pick",
+            Self::Reword => "reword",
+            Self::Fold => "fold",
+            Self::Drop => "drop",
+            Self::Split => "split",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PlanLine {
+    pub action: PlanAction,
+    pub id: String,
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StepAction {
+    Pick,
+    Reword(String),
+    Fold,
+    Drop,
+    Split(Vec<usize>),
+}
+
+pub struct HistoryStep {
+    pub id: Oid,
+    pub action: StepAction,
+}
+
+pub struct HunkInfo {
+    pub path: String,
+    pub header: String,
+    pub preview: Vec<String>,
+}
+
+pub fn render_plan(saves: &[(String, String)]) -> String {
+    let lines: Vec<String> = saves
+        .iter()
+        .map(|(id, summary)| format!("pick {id} {summary}"))
+        .collect();
+    format!("{}\n{PLAN_HELP}", lines.join("\n"))
+}
+
+pub fn check_plan(actions: &[PlanAction]) -> Maybe<()> {
+    match actions.iter().find(|a| **a != PlanAction::Drop) {
+        Some(PlanAction::Fold) => fail!("Cannot fold the first save, there is no save above it."),
+        _ => Ok(()),
+    }
+}
+
+pub fn parse_plan(text: &str) -> Maybe<Vec<PlanLine>> {
+    let mut lines = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.splitn(3, char::is_whitespace);
+        let parsed = match (parts.next().and_then(PlanAction::parse), parts.next()) {
+            (Some(action), Some(id)) => PlanLine {
+                action,
+                id: id.to_string(),
+                message: parts.next().unwrap_or_default().trim().to_string(),
+            },
+            _ => return fail!(format!("Invalid line {} of the plan: {line}", i + 1)),
+        };
+
+        if parsed.action == PlanAction::Reword && parsed.message.is_empty() {
+            return fail!(format!("Missing message to reword {} to.", parsed.id));
+        }
+
+        lines.push(parsed);
+    }
+
+    check_plan(&lines.iter().map(|l| l.action).collect::<Vec<_>>())?;
+
+    Ok(lines)
+}
+
+pub fn render_hunks(summary: &str, hunks: &[HunkInfo]) -> String {
+    let mut lines = vec![format!("# Splitting \"{summary}\"")];
+    for (i, hunk) in hunks.iter().enumerate() {
+        lines.push(format!("{} {} {}", i + 1, hunk.path, hunk.header));
+        lines.extend(hunk.preview.iter().map(|l| format!("#   {l}")));
+    }
+    format!("{}\n{HUNKS_HELP}", lines.join("\n"))
+}
+
+pub fn parse_hunk_groups(text: &str, count: usize) -> Maybe<Vec<usize>> {
+    let mut groups = vec![];
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse::<usize>().ok())
+        {
+            Some(group) if group > 0 => groups.push(group),
+            _ => return fail!(format!("Expected a save number at the start of: {line}")),
+        }
+    }
+
+    if groups.len() != count {
+        return fail!(format!(
+            "Expected {count} hunks but found {}. Keep one line per hunk.",
+            groups.len()
+        ));
+    }
+
+    Ok(groups)
+}
+
+pub fn split_pieces(groups: &[usize]) -> Vec<usize> {
+    let mut pieces = groups.to_vec();
+    pieces.sort_unstable();
+    pieces.dedup();
+    pieces
+}
+
+pub fn with_summary(message: &str, summary: &str) -> String {
+    match message.split_once('\n') {
+        Some((_, rest)) => format!("{summary}\n{rest}"),
+        None => summary.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        PlanAction, PlanLine, parse_hunk_groups, parse_plan, render_plan, split_pieces,
+        with_summary,
+    };
+
+    #[test]
+    fn parses_plan() {
+        let plan = parse_plan(
+            "pick 1a2b3c4d First\n# comment\n\nr 5e6f7a8b Better message\nfold 9c0d1e2f Third\ndrop aaaa\ns bbbb Fifth\n",
+        )
+        .unwrap();
+        let actions: Vec<PlanAction> = plan.iter().map(|l| l.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                PlanAction::Pick,
+                PlanAction::Reword,
+                PlanAction::Fold,
+                PlanAction::Drop,
+                PlanAction::Split,
+            ]
+        );
+        assert_eq!(
+            plan[1],
+            PlanLine {
+                action: PlanAction::Reword,
+                id: "5e6f7a8b".to_string(),
+                message: "Better message".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_plans() {
+        assert!(parse_plan("squash 1a2b3c4d First\n").is_err());
+        assert!(parse_plan("pick\n").is_err());
+        assert!(parse_plan("reword 1a2b3c4d\n").is_err());
+        assert!(parse_plan("drop 1a2b3c4d First\nfold 5e6f7a8b Second\n").is_err());
+    }
+
+    #[test]
+    fn renders_plan_that_parses() {
+        let saves = vec![
+            ("1a2b3c4d".to_string(), "First".to_string()),
+            ("5e6f7a8b".to_string(), "Second".to_string()),
+        ];
+        let plan = parse_plan(&render_plan(&saves)).unwrap();
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[1].message, "Second");
+    }
+
+    #[test]
+    fn parses_hunk_groups() {
+        let text = "# Splitting\n2 a.txt @@ -1 +1 @@\n#   -old\n1 b.txt @@ -4 +4 @@\n2 b.txt @@ -9 +9 @@\n";
+        let groups = parse_hunk_groups(text, 3).unwrap();
+        assert_eq!(groups, vec![2, 1, 2]);
+        assert_eq!(split_pieces(&groups), vec![1, 2]);
+        assert!(parse_hunk_groups(text, 2).is_err());
+        assert!(parse_hunk_groups("0 a.txt\n", 1).is_err());
+    }
+
+    #[test]
+    fn replaces_summary_only() {
+        assert_eq!(
+            with_summary("Old\n\nBody\n\nSigned-off-by: me", "New"),
+            "New\n\nBody\n\nSigned-off-by: me"
+        );
+        assert_eq!(with_summary("Old", "New"), "New");
+    }
+}
@@ -10,24 +10,34 @@ use log::LevelFilter;
 
 use crate::error::Attempt;
 
+mod archive;
 mod branch;
 mod cli;
 mod command;
 mod commit;
+mod config;
 mod consts;
 mod ctx;
+mod deferred;
 mod diff;
 mod editor;
 mod error;
+mod history;
+mod merge_driver;
 mod output;
 mod path;
 mod print;
 mod prompt;
 mod remote;
+mod rerere;
 mod reset;
+mod rules;
 mod save;
+mod state;
+mod structured;
 mod sync;
 mod timer;
+mod tree;
 
 fn main() -> Attempt {
     let cli = Cli::parse();
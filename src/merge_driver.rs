@@ -0,0 +1,129 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use git2::{AttrCheckFlags, AttrValue, Oid, Repository};
+
+use crate::{
+    config::get_config_string,
+    ctx::Ctx,
+    diff::{decode_text, get_union_text},
+    error::{Maybe, inner_fail},
+    print::show_warning,
+    structured::{StructuredMerger, structured_merger},
+};
+
+pub enum MergeDriver {
+    Text,
+    Binary,
+    Union,
+    Structured(&'static str, StructuredMerger),
+    External(String, String),
+}
+
+pub fn write_versions(
+    repo: &Repository,
+    dir: &Path,
+    sides: (Oid, Oid, Oid),
+) -> Maybe<[PathBuf; 3]> {
+    let mut files = [dir.join("base"), dir.join("main"), dir.join("branch")];
+    for (file, oid) in files.iter_mut().zip([sides.0, sides.1, sides.2]) {
+        if oid.is_zero() {
+            std::fs::write(&file, "")?;
+        } else {
+            std::fs::write(&file, repo.find_blob(oid)?.content())?;
+        }
+    }
+    Ok(files)
+}
+
+fn default_driver(path: &str) -> MergeDriver {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| structured_merger(&ext.to_string_lossy()))
+        .map_or(MergeDriver::Text, |(name, merger)| {
+            MergeDriver::Structured(name, merger)
+        })
+}
+
+pub fn select_driver(ctx: &Ctx, path: &str) -> Maybe<MergeDriver> {
+    let attr = ctx
+        .repo
+        .get_attr(Path::new(path), "merge", AttrCheckFlags::FILE_THEN_INDEX)?;
+
+    Ok(match AttrValue::from_string(attr) {
+        AttrValue::False | AttrValue::String("binary") => MergeDriver::Binary,
+        AttrValue::String("text") => MergeDriver::Text,
+        AttrValue::String("union") => MergeDriver::Union,
+        AttrValue::String(name) => match get_config_string(ctx, &format!("merge.{name}.driver"))? {
+            Some(command) => MergeDriver::External(name.to_string(), command),
+            None => MergeDriver::Text,
+        },
+        _ => default_driver(path),
+    })
+}
+
+fn quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+fn run_external(
+    ctx: &Ctx,
+    path: &str,
+    sides: (Oid, Oid, Oid),
+    name: &str,
+    command: &str,
+) -> Maybe<Option<String>> {
+    let dir = tempfile::tempdir()?;
+    let [base, main, branch] = write_versions(&ctx.repo, dir.path(), sides)?;
+
+    let command = command
+        .replace("%O", &quote(&base))
+        .replace("%A", &quote(&main))
+        .replace("%B", &quote(&branch))
+        .replace("%P", &quote(Path::new(path)))
+        .replace("%L", "7");
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .current_dir(ctx.repo.workdir().unwrap_or(dir.path()))
+        .status()
+        .map_err(|_| inner_fail!("Failed to start merge driver."))?;
+
+    if !status.success() {
+        show_warning(ctx, &format!("Merge driver {name} could not merge {path}."));
+        return Ok(None);
+    }
+
+    Ok(Some(decode_text(&std::fs::read(&main)?)))
+}
+
+pub fn run_driver(
+    ctx: &Ctx,
+    path: &str,
+    sides: (Oid, Oid, Oid),
+) -> Maybe<Option<(String, String)>> {
+    let content = |oid: Oid| -> Maybe<String> {
+        if oid.is_zero() {
+            return Ok(String::new());
+        }
+        Ok(decode_text(ctx.repo.find_blob(oid)?.content()))
+    };
+
+    Ok(match select_driver(ctx, path)? {
+        MergeDriver::Text | MergeDriver::Binary => None,
+        MergeDriver::Union => Some((
+            "union".to_string(),
+            get_union_text(&ctx.repo, &sides.0, &sides.1, &sides.2)?,
+        )),
+        MergeDriver::Structured(name, merger) => {
+            merger(&content(sides.0)?, &content(sides.1)?, &content(sides.2)?)
+                .map(|merged| (name.to_string(), merged))
+        }
+        MergeDriver::External(name, command) => {
+            run_external(ctx, path, sides, &name, &command)?.map(|merged| (name, merged))
+        }
+    })
+}
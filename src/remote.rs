@@ -1,7 +1,8 @@
 use std::env;
 
 use git2::{
-    Cred, CredentialType, FetchOptions, ProxyOptions, PushOptions, Remote, RemoteCallbacks,
+    AutotagOption, Cred, CredentialType, Direction, FetchOptions, Oid, ProxyOptions, PushOptions,
+    Remote, RemoteCallbacks,
 };
 
 use crate::{
@@ -10,7 +11,7 @@ use crate::{
     print::show_warning,
 };
 
-fn get_remote_prefix() -> Maybe<String> {
+pub fn get_remote_prefix() -> Maybe<String> {
     match env::var("ITCH_REMOTE_PREFIX") {
         Ok(v) => Ok(v),
         Err(env::VarError::NotPresent) => Ok(whoami::username() + "-"),
@@ -111,6 +112,22 @@ pub fn push_tag(ctx: &Ctx, tag: &str) -> Attempt {
     )
 }
 
+pub fn fetch_tag(ctx: &Ctx, remote_tag: &str) -> Attempt {
+    match get_remote(ctx)? {
+        None => fail!("No remote to fetch from."),
+        Some(mut remote) => {
+            let mut options = setup_fetch_options(ctx);
+            options.download_tags(AutotagOption::None);
+            remote.fetch(
+                &[format!("refs/tags/{remote_tag}")],
+                Some(&mut options),
+                Some("Fetch archive"),
+            )?;
+            Ok(())
+        }
+    }
+}
+
 pub fn pull_main(ctx: &Ctx) -> Attempt {
     match get_remote(ctx)? {
         None => Ok(()),
@@ -258,3 +275,32 @@ pub fn try_delete_remote_branch(ctx: &Ctx, name: &str) {
         show_warning(ctx, &format!("Failed to delete branch on remote ({e})"));
     }
 }
+
+pub fn list_remote_refs(ctx: &Ctx) -> Maybe<Vec<(String, Oid)>> {
+    match get_remote(ctx)? {
+        Some(mut remote) => {
+            let connection = remote.connect_auth(
+                Direction::Fetch,
+                Some(setup_remote_callbacks(ctx)),
+                Some(ProxyOptions::new()),
+            )?;
+            Ok(connection
+                .list()?
+                .iter()
+                .map(|head| (head.name().to_string(), head.oid()))
+                .collect())
+        }
+        None => Ok(vec![]),
+    }
+}
+
+pub fn delete_remote_refs(ctx: &Ctx, refs: &[String]) -> Attempt {
+    if refs.iter().any(|r| r == "refs/heads/main") {
+        return fail!("Refusing to delete main branch.");
+    }
+    if let Some(mut remote) = get_remote(ctx)? {
+        let refspecs: Vec<String> = refs.iter().map(|r| format!(":{r}")).collect();
+        remote.push(&refspecs, Some(&mut setup_push_options(ctx)))?;
+    }
+    Ok(())
+}
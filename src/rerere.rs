@@ -0,0 +1,218 @@
+use git2::{ObjectType, Oid};
+
+use crate::{ctx::Ctx, error::Maybe, state::state_path};
+
+const RERERE_DIR: &str = "rerere";
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Context(&'a str),
+    Hunk(&'a str),
+}
+
+fn split_segments(text: &str) -> Option<Vec<Segment<'_>>> {
+    let mut segments = vec![];
+    let mut start = 0;
+    let mut hunk_start: Option<usize> = None;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        if hunk_start.is_none() && line.starts_with("<<<<<<<") {
+            segments.push(Segment::Context(&text[start..offset]));
+            hunk_start = Some(offset);
+        } else if let Some(h) = hunk_start
+            && line.starts_with(">>>>>>>")
+        {
+            segments.push(Segment::Hunk(&text[h..offset + line.len()]));
+            hunk_start = None;
+            start = offset + line.len();
+        }
+        offset += line.len();
+    }
+
+    if hunk_start.is_some() {
+        return None;
+    }
+
+    segments.push(Segment::Context(&text[start..]));
+    Some(segments)
+}
+
+fn normalize_preimage(text: &str) -> String {
+    let mut normalized = String::new();
+    let mut in_conflict = false;
+    let mut in_base = false;
+
+    for line in text.split_inclusive('\n') {
+        let eol = &line[line.trim_end_matches(['\r', '\n']).len()..];
+        if line.starts_with("<<<<<<<") {
+            in_conflict = true;
+            normalized.push_str("<<<<<<<");
+            normalized.push_str(eol);
+        } else if in_conflict && line.starts_with("|||||||") {
+            in_base = true;
+        } else if in_conflict && line.starts_with("=======") {
+            in_base = false;
+            normalized.push_str(line);
+        } else if in_conflict && line.starts_with(">>>>>>>") {
+            in_conflict = false;
+            normalized.push_str(">>>>>>>");
+            normalized.push_str(eol);
+        } else if !in_base {
+            normalized.push_str(line);
+        }
+    }
+
+    normalized
+}
+
+fn align_hunks<'a>(segments: &[Segment<'a>], resolved: &'a str) -> Option<Vec<(&'a str, &'a str)>> {
+    let mut pairs = vec![];
+    let mut cursor = 0;
+    let mut pending: Option<&str> = None;
+
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Hunk(hunk) => pending = Some(hunk),
+            Segment::Context(context) => {
+                let position = if i == 0 {
+                    resolved.starts_with(context).then_some(0)?
+                } else if i == segments.len() - 1 {
+                    let position = resolved.len().checked_sub(context.len())?;
+                    (position >= cursor && resolved.ends_with(context)).then_some(position)?
+                } else if context.is_empty() {
+                    return None;
+                } else {
+                    cursor + resolved[cursor..].find(context)?
+                };
+
+                if let Some(hunk) = pending.take() {
+                    pairs.push((hunk, &resolved[cursor..position]));
+                }
+                cursor = position + context.len();
+            }
+        }
+    }
+
+    Some(pairs)
+}
+
+fn record_path(ctx: &Ctx, preimage: &str) -> Maybe<std::path::PathBuf> {
+    let key = Oid::hash_object(ObjectType::Blob, preimage.as_bytes())?;
+    let dir = state_path(ctx, RERERE_DIR)?;
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(key.to_string()))
+}
+
+fn read_record(ctx: &Ctx, preimage: &str) -> Maybe<Option<String>> {
+    let path = record_path(ctx, preimage)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(std::fs::read_to_string(path)?))
+}
+
+pub fn record_resolution(ctx: &Ctx, preimage: &str, resolved: &str) -> Maybe<()> {
+    let preimage = &normalize_preimage(preimage);
+    let Some(segments) = split_segments(preimage) else {
+        return Ok(());
+    };
+
+    if !segments.iter().any(|s| matches!(s, Segment::Hunk(_))) {
+        return Ok(());
+    }
+
+    std::fs::write(record_path(ctx, preimage)?, resolved)?;
+
+    if let Some(pairs) = align_hunks(&segments, resolved) {
+        for (hunk, resolution) in pairs {
+            std::fs::write(record_path(ctx, hunk)?, resolution)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn replay_resolution(ctx: &Ctx, preimage: &str) -> Maybe<Option<String>> {
+    let preimage = &normalize_preimage(preimage);
+    let Some(segments) = split_segments(preimage) else {
+        return Ok(None);
+    };
+
+    if !segments.iter().any(|s| matches!(s, Segment::Hunk(_))) {
+        return Ok(None);
+    }
+
+    if let Some(resolved) = read_record(ctx, preimage)? {
+        return Ok(Some(resolved));
+    }
+
+    let mut resolved = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Context(context) => resolved.push_str(context),
+            Segment::Hunk(hunk) => match read_record(ctx, hunk)? {
+                Some(resolution) => resolved.push_str(&resolution),
+                None => return Ok(None),
+            },
+        }
+    }
+
+    Ok(Some(resolved))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Segment, align_hunks, normalize_preimage, split_segments};
+
+    const PREIMAGE: &str =
+        "a\n<<<<<<<\nb\n=======\nc\n>>>>>>>\nd\n<<<<<<<\ne\n=======\nf\n>>>>>>>\n";
+
+    #[test]
+    fn splits_hunks() {
+        assert_eq!(
+            split_segments(PREIMAGE).unwrap(),
+            vec![
+                Segment::Context("a\n"),
+                Segment::Hunk("<<<<<<<\nb\n=======\nc\n>>>>>>>\n"),
+                Segment::Context("d\n"),
+                Segment::Hunk("<<<<<<<\ne\n=======\nf\n>>>>>>>\n"),
+                Segment::Context(""),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalizes_labels_and_base() {
+        assert_eq!(
+            normalize_preimage(
+                "a\r\n<<<<<<< main (abc1234)\r\nb\r\n||||||| base\r\nx\r\n=======\r\nc\r\n>>>>>>> feat (def5678 Save)\r\n"
+            ),
+            "a\r\n<<<<<<<\r\nb\r\n=======\r\nc\r\n>>>>>>>\r\n"
+        );
+    }
+
+    #[test]
+    fn rejects_unclosed_hunk() {
+        assert!(split_segments("a\n<<<<<<<\nb\n").is_none());
+    }
+
+    #[test]
+    fn aligns_resolution() {
+        let segments = split_segments(PREIMAGE).unwrap();
+        let pairs = align_hunks(&segments, "a\nb\nc\nd\nf\n").unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("<<<<<<<\nb\n=======\nc\n>>>>>>>\n", "b\nc\n"),
+                ("<<<<<<<\ne\n=======\nf\n>>>>>>>\n", "f\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_edited_context() {
+        let segments = split_segments(PREIMAGE).unwrap();
+        assert!(align_hunks(&segments, "z\nb\nd\nf\n").is_none());
+    }
+}
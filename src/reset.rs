@@ -14,12 +14,16 @@ fn is_temp_commit(c: &Commit) -> bool {
             .is_some_and(|m| m.starts_with(TEMP_COMMIT_PREFIX))
 }
 
-pub fn pop_and_reset(ctx: &Ctx) -> Attempt {
-    let mut commit = ctx.repo.head()?.peel_to_commit()?;
-
+pub fn skip_temp_commits(commit: Commit<'_>) -> Commit<'_> {
+    let mut commit = commit;
     while is_temp_commit(&commit) {
         commit = commit.parents().next().unwrap();
     }
+    commit
+}
+
+pub fn pop_and_reset(ctx: &Ctx) -> Attempt {
+    let commit = skip_temp_commits(ctx.repo.head()?.peel_to_commit()?);
 
     ctx.repo
         .reset(&commit.into_object(), ResetType::Mixed, None)?;
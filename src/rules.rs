@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use glob::Pattern;
+
+use crate::{
+    ctx::Ctx,
+    error::{Maybe, fail},
+};
+
+const RULES_PATH: &str = ".itch/conflicts";
+
+#[derive(Debug, PartialEq)]
+pub enum RuleAction {
+    Keep,
+    Reset,
+    Union,
+    Run(String),
+}
+
+pub struct ConflictRule {
+    pub pattern: String,
+    matcher: Pattern,
+    pub action: RuleAction,
+}
+
+impl ConflictRule {
+    pub fn matches(&self, path: &str) -> bool {
+        if self.pattern.contains('/') {
+            return self.matcher.matches(path);
+        }
+        Path::new(path)
+            .file_name()
+            .is_some_and(|name| self.matcher.matches(&name.to_string_lossy()))
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.action {
+            RuleAction::Keep => format!("{} keep", self.pattern),
+            RuleAction::Reset => format!("{} reset", self.pattern),
+            RuleAction::Union => format!("{} union", self.pattern),
+            RuleAction::Run(command) => format!("{} run {command}", self.pattern),
+        }
+    }
+}
+
+fn parse_action(text: &str) -> Option<RuleAction> {
+    match text.split_once(' ') {
+        Some(("run", command)) if !command.trim().is_empty() => {
+            Some(RuleAction::Run(command.trim().to_string()))
+        }
+        None => match text {
+            "keep" => Some(RuleAction::Keep),
+            "reset" => Some(RuleAction::Reset),
+            "union" => Some(RuleAction::Union),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn parse_rules(text: &str) -> Maybe<Vec<ConflictRule>> {
+    let mut rules = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parsed = line
+            .split_once(char::is_whitespace)
+            .and_then(|(pattern, action)| {
+                Some(ConflictRule {
+                    pattern: pattern.to_string(),
+                    matcher: Pattern::new(pattern).ok()?,
+                    action: parse_action(action.trim())?,
+                })
+            });
+
+        match parsed {
+            Some(rule) => rules.push(rule),
+            None => {
+                return fail!(format!(
+                    "Invalid rule on line {} of {RULES_PATH}: {line}",
+                    i + 1
+                ));
+            }
+        }
+    }
+
+    Ok(rules)
+}
+
+pub fn load_rules(ctx: &Ctx) -> Maybe<Vec<ConflictRule>> {
+    let Some(path) = ctx.repo.workdir().map(|dir| dir.join(RULES_PATH)) else {
+        return Ok(vec![]);
+    };
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    parse_rules(&std::fs::read_to_string(path)?)
+}
+
+pub fn find_rule<'a>(rules: &'a [ConflictRule], path: &str) -> Option<&'a ConflictRule> {
+    rules.iter().find(|rule| rule.matches(path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConflictRule, RuleAction, find_rule, parse_rules};
+
+    #[test]
+    fn parses_actions() {
+        let rules = parse_rules(
+            "# lockfiles\nCargo.lock reset\n\nsrc/gen/*.rs keep\nCHANGELOG.md union\n*.snap run ./regen.sh --all\n",
+        )
+        .unwrap();
+        let actions: Vec<&RuleAction> = rules.iter().map(|r| &r.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                &RuleAction::Reset,
+                &RuleAction::Keep,
+                &RuleAction::Union,
+                &RuleAction::Run("./regen.sh --all".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_action() {
+        assert!(parse_rules("Cargo.lock theirs\n").is_err());
+        assert!(parse_rules("Cargo.lock\n").is_err());
+    }
+
+    #[test]
+    fn matches_basename_or_path() {
+        let rules = parse_rules("Cargo.lock reset\nsrc/gen/*.rs keep\n").unwrap();
+        assert_eq!(
+            find_rule(&rules, "crates/a/Cargo.lock").map(ConflictRule::describe),
+            Some("Cargo.lock reset".to_string())
+        );
+        assert!(find_rule(&rules, "src/gen/api.rs").is_some());
+        assert!(find_rule(&rules, "other/src/gen/api.rs").is_none());
+    }
+}
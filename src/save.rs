@@ -1,17 +1,28 @@
-use git2::{ErrorCode, IndexAddOption};
+use git2::IndexAddOption;
 
 use crate::{
     cli::SaveArgs,
+    config::get_config_string,
     consts::TEMP_COMMIT_PREFIX,
     ctx::Ctx,
     error::{Attempt, Maybe},
 };
 
 pub fn include_footer(ctx: &Ctx, full_message: &str) -> Maybe<String> {
-    match ctx.repo.config()?.get_string("itch.footer") {
-        Ok(v) => Ok(format!("{full_message}\n\n{v}")),
-        Err(e) if { e.code() == ErrorCode::NotFound } => Ok(full_message.to_string()),
-        Err(e) => Err(e.into()),
+    match get_config_string(ctx, "itch.footer")? {
+        Some(v) => Ok(format!("{full_message}\n\n{v}")),
+        None => Ok(full_message.to_string()),
+    }
+}
+
+pub fn strip_footer(ctx: &Ctx, full_message: &str) -> Maybe<String> {
+    let message = full_message.trim_end();
+    match get_config_string(ctx, "itch.footer")? {
+        Some(v) => Ok(message
+            .strip_suffix(v.trim_end())
+            .map_or(message, str::trim_end)
+            .to_string()),
+        None => Ok(message.to_string()),
     }
 }
 
@@ -63,6 +74,7 @@ pub fn save_temp(ctx: &Ctx, message: String) -> Attempt {
         ctx,
         &SaveArgs {
             message: vec![TEMP_COMMIT_PREFIX.to_string(), message],
+            allow_conflicts: true,
         },
         true,
     )
@@ -0,0 +1,9 @@
+use std::path::PathBuf;
+
+use crate::{ctx::Ctx, error::Maybe};
+
+pub fn state_path(ctx: &Ctx, name: &str) -> Maybe<PathBuf> {
+    let dir = ctx.repo.path().join("itch");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(name))
+}
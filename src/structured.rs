@@ -0,0 +1,334 @@
+trait Structured: Clone + PartialEq + Sized {
+    type Key: Clone + PartialEq;
+
+    fn entries(&self) -> Option<Vec<(Self::Key, Self)>>;
+    fn from_entries(entries: Vec<(Self::Key, Self)>) -> Self;
+}
+
+impl Structured for serde_json::Value {
+    type Key = String;
+
+    fn entries(&self) -> Option<Vec<(String, Self)>> {
+        self.as_object()
+            .map(|map| map.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+
+    fn from_entries(entries: Vec<(String, Self)>) -> Self {
+        Self::Object(entries.into_iter().collect())
+    }
+}
+
+impl Structured for toml::Value {
+    type Key = String;
+
+    fn entries(&self) -> Option<Vec<(String, Self)>> {
+        self.as_table()
+            .map(|table| table.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+
+    fn from_entries(entries: Vec<(String, Self)>) -> Self {
+        Self::Table(entries.into_iter().collect())
+    }
+}
+
+impl Structured for serde_yaml::Value {
+    type Key = serde_yaml::Value;
+
+    fn entries(&self) -> Option<Vec<(Self, Self)>> {
+        self.as_mapping()
+            .map(|map| map.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+
+    fn from_entries(entries: Vec<(Self, Self)>) -> Self {
+        Self::Mapping(entries.into_iter().collect())
+    }
+}
+
+fn find<'a, T: Structured>(entries: &'a [(T::Key, T)], key: &T::Key) -> Option<&'a T> {
+    entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+enum Entry<T> {
+    Value(T),
+    Removed,
+}
+
+impl<T: Clone> Entry<T> {
+    fn from(value: Option<&T>) -> Self {
+        value.map_or(Self::Removed, |v| Self::Value(v.clone()))
+    }
+}
+
+fn merge_entry<T: Structured>(
+    base: Option<&T>,
+    main: Option<&T>,
+    branch: Option<&T>,
+) -> Option<Entry<T>> {
+    if main == branch || base == branch {
+        return Some(Entry::from(main));
+    }
+    if base == main {
+        return Some(Entry::from(branch));
+    }
+    match (base, main, branch) {
+        (Some(base), Some(main), Some(branch)) => {
+            merge_values(base, main, branch).map(Entry::Value)
+        }
+        (None, Some(main), Some(branch)) => {
+            merge_values(&T::from_entries(vec![]), main, branch).map(Entry::Value)
+        }
+        _ => None,
+    }
+}
+
+fn merge_values<T: Structured>(base: &T, main: &T, branch: &T) -> Option<T> {
+    if main == branch || base == branch {
+        return Some(main.clone());
+    }
+    if base == main {
+        return Some(branch.clone());
+    }
+
+    let (base_entries, main_entries, branch_entries) =
+        (base.entries()?, main.entries()?, branch.entries()?);
+
+    let mut keys: Vec<T::Key> = main_entries.iter().map(|(k, _)| k.clone()).collect();
+    for (key, _) in &branch_entries {
+        if !keys.contains(key) {
+            keys.push(key.clone());
+        }
+    }
+    for (key, _) in &base_entries {
+        if !keys.contains(key) {
+            keys.push(key.clone());
+        }
+    }
+
+    let mut merged = vec![];
+    for key in keys {
+        let entry = merge_entry(
+            find(&base_entries, &key),
+            find(&main_entries, &key),
+            find(&branch_entries, &key),
+        )?;
+        if let Entry::Value(value) = entry {
+            merged.push((key, value));
+        }
+    }
+
+    Some(T::from_entries(merged))
+}
+
+fn detect_indent(text: &str) -> String {
+    text.lines()
+        .find_map(|line| {
+            let trimmed = line.trim_start();
+            (!trimmed.is_empty() && trimmed.len() < line.len())
+                .then(|| line[..line.len() - trimmed.len()].to_string())
+        })
+        .unwrap_or_else(|| "  ".to_string())
+}
+
+fn with_trailing_newline(mut text: String, like: &str) -> String {
+    let wants_newline = like.ends_with('\n');
+    if wants_newline && !text.ends_with('\n') {
+        text.push('\n');
+    } else if !wants_newline {
+        while text.ends_with('\n') {
+            text.pop();
+        }
+    }
+    text
+}
+
+fn has_hash_comments(text: &str) -> bool {
+    text.lines().any(|line| {
+        let mut quote: Option<char> = None;
+        let mut previous = ' ';
+        for c in line.chars() {
+            if let Some(q) = quote {
+                if c == q && previous != '\\' {
+                    quote = None;
+                }
+            } else if c == '"' || c == '\'' {
+                quote = Some(c);
+            } else if c == '#' && previous.is_whitespace() {
+                return true;
+            }
+            previous = c;
+        }
+        false
+    })
+}
+
+fn merge_with<T: Structured>(
+    (base, main, branch): (&str, &str, &str),
+    parse: impl Fn(&str) -> Option<T>,
+    render: impl Fn(&T) -> Option<String>,
+) -> Option<String> {
+    let main_value = parse(main)?;
+    if render(&main_value)? != main {
+        return None;
+    }
+
+    let merged = merge_values(&parse(base)?, &main_value, &parse(branch)?)?;
+    render(&merged)
+}
+
+fn merge_json(base: &str, main: &str, branch: &str) -> Option<String> {
+    use serde::Serialize;
+
+    let parse = |text: &str| -> Option<serde_json::Value> {
+        if text.trim().is_empty() {
+            return Some(serde_json::Value::Object(serde_json::Map::new()));
+        }
+        serde_json::from_str(text).ok()
+    };
+
+    let indent = detect_indent(main);
+    let render = |value: &serde_json::Value| -> Option<String> {
+        let mut out = vec![];
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+        value.serialize(&mut serializer).ok()?;
+        Some(with_trailing_newline(String::from_utf8(out).ok()?, main))
+    };
+
+    merge_with((base, main, branch), parse, render)
+}
+
+fn merge_toml(base: &str, main: &str, branch: &str) -> Option<String> {
+    if [base, main, branch].iter().any(|t| has_hash_comments(t)) {
+        return None;
+    }
+
+    merge_with(
+        (base, main, branch),
+        |text| toml::from_str::<toml::Value>(text).ok(),
+        |value| Some(with_trailing_newline(toml::to_string(value).ok()?, main)),
+    )
+}
+
+fn merge_yaml(base: &str, main: &str, branch: &str) -> Option<String> {
+    if [base, main, branch].iter().any(|t| has_hash_comments(t)) {
+        return None;
+    }
+
+    let parse = |text: &str| -> Option<serde_yaml::Value> {
+        if text.trim().is_empty() {
+            return Some(serde_yaml::Value::Mapping(serde_yaml::Mapping::new()));
+        }
+        serde_yaml::from_str(text).ok()
+    };
+
+    merge_with((base, main, branch), parse, |value| {
+        Some(with_trailing_newline(
+            serde_yaml::to_string(value).ok()?,
+            main,
+        ))
+    })
+}
+
+pub type StructuredMerger = fn(&str, &str, &str) -> Option<String>;
+
+pub fn structured_merger(extension: &str) -> Option<(&'static str, StructuredMerger)> {
+    match extension.to_ascii_lowercase().as_str() {
+        "json" => Some(("json", merge_json)),
+        "toml" => Some(("toml", merge_toml)),
+        "yaml" | "yml" => Some(("yaml", merge_yaml)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{has_hash_comments, merge_json, merge_toml, merge_yaml};
+
+    #[test]
+    fn json_adds_different_keys() {
+        let merged = merge_json(
+            "{\n    \"a\": 1\n}\n",
+            "{\n    \"a\": 1,\n    \"main\": true\n}\n",
+            "{\n    \"a\": 1,\n    \"branch\": true\n}\n",
+        );
+        assert_eq!(
+            merged.as_deref(),
+            Some("{\n    \"a\": 1,\n    \"main\": true,\n    \"branch\": true\n}\n")
+        );
+    }
+
+    #[test]
+    fn json_nested_and_removed_keys() {
+        let merged = merge_json(
+            "{\n  \"a\": {\n    \"x\": 1,\n    \"y\": 2\n  },\n  \"gone\": 1\n}\n",
+            "{\n  \"a\": {\n    \"x\": 5,\n    \"y\": 2\n  },\n  \"gone\": 1\n}\n",
+            "{\n  \"a\": {\n    \"x\": 1,\n    \"y\": 7\n  }\n}\n",
+        );
+        assert_eq!(
+            merged.as_deref(),
+            Some("{\n  \"a\": {\n    \"x\": 5,\n    \"y\": 7\n  }\n}\n")
+        );
+    }
+
+    #[test]
+    fn json_keeps_unusual_formatting() {
+        assert!(merge_json(r#"{"a": 1}"#, r#"{"a": 1, "b": 2}"#, r#"{"a": 1, "c": 3}"#).is_none());
+        assert!(
+            merge_json(
+                "{\n  \"a\": 1.50\n}\n",
+                "{\n  \"a\": 1.50,\n  \"b\": 2\n}\n",
+                "{\n  \"a\": 1.50,\n  \"c\": 3\n}\n",
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn toml_keeps_inline_tables() {
+        assert!(
+            merge_toml(
+                "dep = { version = \"1\" }\n",
+                "dep = { version = \"1\" }\nb = 2\n",
+                "dep = { version = \"1\" }\nc = 3\n",
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn json_same_key_conflicts() {
+        assert!(merge_json(r#"{"a": 1}"#, r#"{"a": 2}"#, r#"{"a": 3}"#).is_none());
+    }
+
+    #[test]
+    fn json_invalid_falls_back() {
+        assert!(merge_json("{", r#"{"a": 2}"#, r#"{"b": 3}"#).is_none());
+    }
+
+    #[test]
+    fn toml_merges_tables() {
+        let merged = merge_toml(
+            "[deps]\na = \"1\"\n",
+            "[deps]\na = \"1\"\nb = \"2\"\n",
+            "[deps]\na = \"1\"\nc = \"3\"\n",
+        );
+        assert_eq!(
+            merged.as_deref(),
+            Some("[deps]\na = \"1\"\nb = \"2\"\nc = \"3\"\n")
+        );
+    }
+
+    #[test]
+    fn yaml_merges_mappings() {
+        let merged = merge_yaml("a: 1\n", "a: 1\nb: 2\n", "a: 1\nc: 3\n");
+        assert_eq!(merged.as_deref(), Some("a: 1\nb: 2\nc: 3\n"));
+    }
+
+    #[test]
+    fn comments_are_not_dropped() {
+        assert!(has_hash_comments("# top\na: 1\n"));
+        assert!(has_hash_comments("a: 1 # trailing\n"));
+        assert!(!has_hash_comments("color: \"#fff\"\nb: a#b\n"));
+        assert!(merge_yaml("a: 1\n", "# note\na: 1\nb: 2\n", "a: 1\nc: 3\n").is_none());
+    }
+}
@@ -1,30 +1,109 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
-#[derive(Clone, Debug)]
+use serde::Deserialize;
+
+use crate::cli::{MergeCommits, SyncMode};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ResolutionChoice {
+    #[serde(alias = "keep")]
     Incoming,
+    #[serde(alias = "reset")]
     Base,
     Later,
     Manual(String),
 }
 
+impl ResolutionChoice {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "keep" | "incoming" => Some(Self::Incoming),
+            "reset" | "base" => Some(Self::Base),
+            "later" => Some(Self::Later),
+            _ => None,
+        }
+    }
+}
+
 pub type ResolutionMap = HashMap<String, ResolutionChoice>;
 
-pub struct MergeConflict {
+pub struct ConflictPath {
     pub path: String,
+    pub note: Option<String>,
+}
+
+impl fmt::Display for ConflictPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.note {
+            Some(note) => write!(f, "{} ({note})", self.path),
+            None => write!(f, "{}", self.path),
+        }
+    }
+}
+
+pub struct MergeConflict {
+    pub path: ConflictPath,
     pub main_content: String,
     pub branch_content: String,
+    pub base_content: String,
     pub merge_content: String,
 }
 
 pub enum Conflict {
-    MainDeletion(String),
-    BranchDeletion(String),
+    MainDeletion(ConflictPath),
+    BranchDeletion(ConflictPath),
     Merge(MergeConflict),
-    OpaqueMerge(String),
+    OpaqueMerge(ConflictPath),
+}
+
+impl Conflict {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::MainDeletion(path) | Self::BranchDeletion(path) | Self::OpaqueMerge(path) => {
+                &path.path
+            }
+            Self::Merge(info) => &info.path.path,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SyncOptions<'a> {
+    pub resolutions: Option<&'a ResolutionMap>,
+    pub no_prompt: bool,
+    pub default_resolution: Option<ResolutionChoice>,
+    pub merges: Option<MergeCommits>,
+    pub mode: Option<SyncMode>,
+    pub upstream: Option<&'a str>,
 }
 
 pub enum SyncDetails {
     Complete,
     Conflicted(Vec<Conflict>),
 }
+
+pub struct ConflictPreview {
+    pub save: String,
+    pub paths: Vec<String>,
+}
+
+pub fn count_conflicting_files(previews: &[ConflictPreview]) -> usize {
+    let mut paths: Vec<&String> = previews.iter().flat_map(|p| &p.paths).collect();
+    paths.sort();
+    paths.dedup();
+    paths.len()
+}
+
+pub fn conflict_badge(count: usize) -> Option<String> {
+    match count {
+        0 => None,
+        1 => Some("sync will conflict in 1 file".to_string()),
+        n => Some(format!("sync will conflict in {n} files")),
+    }
+}
+
+pub struct SyncReport {
+    pub details: SyncDetails,
+    pub auto_resolved: Vec<String>,
+}
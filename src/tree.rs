@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use git2::{FileMode, Oid, Repository, Tree, TreeEntry, build::TreeUpdateBuilder};
+use glob::Pattern;
+
+use crate::error::{Maybe, fail};
+
+const MODE_TYPE_MASK: i32 = 0o170_000;
+const MODE_TREE: i32 = 0o040_000;
+const MODE_BLOB: i32 = 0o100_000;
+const MODE_LINK: i32 = 0o120_000;
+const MODE_COMMIT: i32 = 0o160_000;
+const MODE_GROUP_WRITABLE: i32 = 0o100_664;
+
+pub fn file_mode(raw: i32) -> Option<FileMode> {
+    match raw & MODE_TYPE_MASK {
+        MODE_TREE => Some(FileMode::Tree),
+        MODE_LINK => Some(FileMode::Link),
+        MODE_COMMIT => Some(FileMode::Commit),
+        MODE_BLOB if raw == MODE_GROUP_WRITABLE => Some(FileMode::BlobGroupWritable),
+        MODE_BLOB if raw & 0o111 != 0 => Some(FileMode::BlobExecutable),
+        MODE_BLOB => Some(FileMode::Blob),
+        _ => None,
+    }
+}
+
+pub fn entry_mode(entry: &TreeEntry) -> Maybe<FileMode> {
+    match file_mode(entry.filemode()) {
+        Some(mode) => Ok(mode),
+        None => fail!(format!(
+            "Unsupported file mode {:o} for {}.",
+            entry.filemode(),
+            entry.name().unwrap_or_default()
+        )),
+    }
+}
+
+fn matches_filter(path: &Path, filter: &str) -> bool {
+    path.starts_with(filter.trim_end_matches('/'))
+        || Pattern::new(filter).is_ok_and(|p| p.matches_path(path))
+}
+
+pub fn changed_paths(
+    repo: &Repository,
+    from: &Tree,
+    to: &Tree,
+    filters: &[String],
+) -> Maybe<Vec<PathBuf>> {
+    let diff = repo.diff_tree_to_tree(Some(from), Some(to), None)?;
+    let mut paths = vec![];
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path()
+                && filters.iter().any(|f| matches_filter(path, f))
+                && !paths.iter().any(|p| p == path)
+            {
+                paths.push(path.to_path_buf());
+            }
+        }
+    }
+    Ok(paths)
+}
+
+pub fn copy_paths(
+    repo: &Repository,
+    paths: &[PathBuf],
+    source: &Tree,
+    target: &Tree,
+) -> Maybe<Oid> {
+    let mut builder = TreeUpdateBuilder::new();
+    for path in paths {
+        match source.get_path(path) {
+            Ok(entry) => {
+                builder.upsert(path, entry.id(), entry_mode(&entry)?);
+            }
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                if target.get_path(path).is_ok() {
+                    builder.remove(path);
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(builder.create_updated(repo, target)?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use git2::{FileMode, Oid, Repository, Tree};
+
+    use super::{changed_paths, copy_paths, file_mode};
+    use crate::diff::merge_tests::init_repo;
+
+    fn make_tree<'a>(repo: &'a Repository, entries: &[(&str, Oid, i32)]) -> Tree<'a> {
+        let mut builder = repo.treebuilder(None).unwrap();
+        for (name, id, mode) in entries {
+            builder.insert(name, *id, *mode).unwrap();
+        }
+        repo.find_tree(builder.write().unwrap()).unwrap()
+    }
+
+    fn source_tree(repo: &Repository) -> Tree<'_> {
+        let blob = repo.blob(b"text\n").unwrap();
+        let script = repo.blob(b"#!/bin/sh\n").unwrap();
+        let link = repo.blob(b"file.txt").unwrap();
+        let submodule = Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        let inner = make_tree(repo, &[("inner.txt", blob, 0o100_644)]);
+        make_tree(
+            repo,
+            &[
+                ("file.txt", blob, 0o100_644),
+                ("run.sh", script, 0o100_755),
+                ("link", link, 0o120_000),
+                ("sub", submodule, 0o160_000),
+                ("dir", inner.id(), 0o040_000),
+            ],
+        )
+    }
+
+    fn copy_one(path: &str) -> (i32, bool) {
+        let (_dir, repo) = init_repo();
+        let source = source_tree(&repo);
+        let target = make_tree(&repo, &[]);
+        let copied = repo
+            .find_tree(copy_paths(&repo, &[PathBuf::from(path)], &source, &target).unwrap())
+            .unwrap();
+        let entry = copied.get_path(path.as_ref()).unwrap();
+        let original = source.get_path(path.as_ref()).unwrap();
+        (entry.filemode(), entry.id() == original.id())
+    }
+
+    #[test]
+    fn maps_raw_modes() {
+        assert_eq!(file_mode(0o100_644), Some(FileMode::Blob));
+        assert_eq!(file_mode(0o100_600), Some(FileMode::Blob));
+        assert_eq!(file_mode(0o100_664), Some(FileMode::BlobGroupWritable));
+        assert_eq!(file_mode(0o100_755), Some(FileMode::BlobExecutable));
+        assert_eq!(file_mode(0o100_775), Some(FileMode::BlobExecutable));
+        assert_eq!(file_mode(0o120_000), Some(FileMode::Link));
+        assert_eq!(file_mode(0o160_000), Some(FileMode::Commit));
+        assert_eq!(file_mode(0o040_000), Some(FileMode::Tree));
+        assert_eq!(file_mode(0), None);
+        assert_eq!(file_mode(0o060_000), None);
+    }
+
+    #[test]
+    fn copies_blob() {
+        assert_eq!(copy_one("file.txt"), (0o100_644, true));
+    }
+
+    #[test]
+    fn copies_executable() {
+        assert_eq!(copy_one("run.sh"), (0o100_755, true));
+    }
+
+    #[test]
+    fn copies_symlink() {
+        assert_eq!(copy_one("link"), (0o120_000, true));
+    }
+
+    #[test]
+    fn copies_submodule() {
+        assert_eq!(copy_one("sub"), (0o160_000, true));
+    }
+
+    #[test]
+    fn copies_tree() {
+        assert_eq!(copy_one("dir"), (0o040_000, true));
+    }
+
+    #[test]
+    fn removes_missing_paths() {
+        let (_dir, repo) = init_repo();
+        let source = make_tree(&repo, &[]);
+        let target = source_tree(&repo);
+        let paths = [PathBuf::from("run.sh"), PathBuf::from("dir/inner.txt")];
+        let copied = repo
+            .find_tree(copy_paths(&repo, &paths, &source, &target).unwrap())
+            .unwrap();
+        assert!(copied.get_name("run.sh").is_none());
+        assert!(copied.get_name("dir").is_none());
+        assert_eq!(copied.get_name("sub").unwrap().filemode(), 0o160_000);
+    }
+
+    #[test]
+    fn filters_changed_paths() {
+        let (_dir, repo) = init_repo();
+        let from = make_tree(&repo, &[]);
+        let to = source_tree(&repo);
+        let changed = |filters: &[&str]| {
+            let filters: Vec<String> = filters.iter().map(ToString::to_string).collect();
+            changed_paths(&repo, &from, &to, &filters).unwrap()
+        };
+        assert_eq!(changed(&["dir/"]), vec![PathBuf::from("dir/inner.txt")]);
+        assert_eq!(changed(&["*.sh"]), vec![PathBuf::from("run.sh")]);
+        assert_eq!(changed(&["*.txt"]).len(), 2);
+        assert!(changed(&["missing"]).is_empty());
+    }
+}